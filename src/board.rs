@@ -1,6 +1,11 @@
 // board.rs
 
 use std::fmt;
+use std::str::FromStr;
+use crate::evaluate::piece_value;
+use crate::move_tables::gen_tables;
+use crate::move_tables::BETWEEN;
+use crate::move_tables::LINE;
 use crate::move_tables::KING_ATTACKS;
 use crate::move_tables::KNIGHT_ATTACKS;
 use crate::move_tables::PAWN_ATTACKS;
@@ -15,13 +20,14 @@ use crate::sliding_attacks::get_rook_rays;
 use crate::square::ALL_SQUARES;
 use crate::square::Square;
 use crate::piece_move::Move;
+use crate::zobrist::*;
 
 
 pub const STARTPOS: &str = 
     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum FenParseError {
     InvalidPosition,
     SideToMove,
@@ -29,6 +35,57 @@ pub enum FenParseError {
     EnPassant,
     HalfMove,
     Ply,
+    /// A pawn stands on the first or last rank.
+    PawnOnBackRank,
+    /// The side that just moved is still in check.
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self {
+            FenParseError::InvalidPosition => "piece placement",
+            FenParseError::SideToMove      => "side to move",
+            FenParseError::Castling        => "castling rights",
+            FenParseError::EnPassant       => "en passant square",
+            FenParseError::HalfMove        => "halfmove clock",
+            FenParseError::Ply             => "fullmove number",
+            FenParseError::PawnOnBackRank  => return write!(f, "invalid FEN, pawn on the first or last rank"),
+            FenParseError::OpponentInCheck => return write!(f, "invalid FEN, side not to move is in check"),
+        };
+        write!(f, "invalid FEN {field}")
+    }
+}
+
+impl std::error::Error for FenParseError {}
+
+/// Castling rights kept when a move starts or ends on each square. Touching a
+/// king or rook home square clears the matching rights.
+const CASTLING_MASK: [u8; 64] = [
+    0b1110, 0b1111, 0b1111, 0b1111, 0b1100, 0b1111, 0b1111, 0b1101,
+    0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111,
+    0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111,
+    0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111,
+    0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111,
+    0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111,
+    0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111, 0b1111,
+    0b1011, 0b1111, 0b1111, 0b1111, 0b0011, 0b1111, 0b1111, 0b0111,
+];
+
+/// Bitboard of the light squares, a8 is light.
+const LIGHT_SQUARES: u64 = 0xAA55AA55AA55AA55;
+
+/// State destroyed by `make_move` that `unmake_move` needs to restore.
+#[derive(Clone, Copy, Debug)]
+pub struct Undo {
+    /// Bitboard index of the captured piece, if any.
+    pub captured: Option<usize>,
+    castling_rights: u8,
+    en_passant: Square,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    hash: u64,
+    in_check: bool,
 }
 
 /// Tracks current game state
@@ -39,6 +96,11 @@ pub struct Board {
     castling_rights: u8,
     en_passant: Square,
     in_check: bool,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    hash: u64,
+    /// Hashes of the earlier positions of the game, oldest first.
+    history: Vec<u64>,
     pub pseudo_moves: MoveList,
 }
 
@@ -47,15 +109,25 @@ pub struct Board {
 impl Board {
 
     /// Creates a new board state from FEN string.
+    #[allow(clippy::single_match)]
     pub fn new(fen: &str) -> Result<Board, FenParseError> {
 
+        // make sure the attack tables exist before anything uses them
+        gen_tables();
+
         // define empty bitboard set
         let mut bitboards: [Bitboard; 12] = [EMPTY; 12];
 
         // fill bitboards
-        let position = fen.split_whitespace().nth(0).unwrap();
+        let position = fen
+            .split_whitespace()
+            .nth(0)
+            .ok_or(FenParseError::InvalidPosition)?;
         let mut index = 0u64;
         for c in position.chars() {
+            if index >= 64 && c != '/' {
+                return Err(FenParseError::InvalidPosition);
+            }
             match c {
                 'K' => bitboards[0]  |= 1u64 << index,
                 'Q' => bitboards[1]  |= 1u64 << index,
@@ -69,8 +141,8 @@ impl Board {
                 'b' => bitboards[9]  |= 1u64 << index,
                 'n' => bitboards[10] |= 1u64 << index,
                 'p' => bitboards[11] |= 1u64 << index,
-                x if x.is_numeric() => {
-                    index += x.to_string().parse::<u64>().unwrap();
+                x if ('1'..='8').contains(&x) => {
+                    index += x.to_digit(10).unwrap() as u64;
                     continue;
                 }
                 '/' => continue,
@@ -79,20 +151,28 @@ impl Board {
             if c.is_alphabetic() {index += 1}
         }
 
-        // make sure two kings are on the board
-        if bitboards[0].count_bits() != 1 || 
+
+        // make sure every square is accounted for and two kings are on the board
+        if index != 64 ||
+           bitboards[0].count_bits() != 1 || 
            bitboards[6].count_bits() != 1 {
             return Err(FenParseError::InvalidPosition);
         }
 
-        let to_move = match fen.split_whitespace().nth(1).unwrap() {
+        // the search can't cope with pawns that can never move or promote
+        let back_ranks = 0xFF00_0000_0000_00FFu64;
+        if (bitboards[5].to_int() | bitboards[11].to_int()) & back_ranks != 0 {
+            return Err(FenParseError::PawnOnBackRank);
+        }
+
+        let to_move = match fen.split_whitespace().nth(1).ok_or(FenParseError::SideToMove)? {
             "w" => Color::WHITE,
             "b" => Color::BLACK,
              _ => { return Err(FenParseError::SideToMove); }
         };
 
         let mut flag = false;
-        let castling = fen.split_whitespace().nth(2).unwrap();
+        let castling = fen.split_whitespace().nth(2).ok_or(FenParseError::Castling)?;
         let mut castling_rights: u8 = 0;
 
         // Check if there are characters except KQkd- in castling field
@@ -135,24 +215,53 @@ impl Board {
         }
         if flag { return Err(FenParseError::Castling) } 
 
+        // every right needs its king and rook still on their home squares
+        let homes = [
+            (8, 0, Square::E1, Square::H1),
+            (4, 0, Square::E1, Square::A1),
+            (2, 6, Square::E8, Square::H8),
+            (1, 6, Square::E8, Square::A8),
+        ];
+        for (right, king, king_home, rook_home) in homes {
+            if castling_rights & right != 0 &&
+               (bitboards[king].get_bit_by_square(king_home) == 0 ||
+                bitboards[king + 2].get_bit_by_square(rook_home) == 0) {
+                return Err(FenParseError::Castling);
+            }
+        }
 
 
-        let en_pass_square = fen.split_whitespace().nth(3).unwrap();
-        let en_passant: Square;
-        if en_pass_square == "-" {
-            en_passant = Square::NO_SQUARE;
+        let en_pass_square = fen.split_whitespace().nth(3).ok_or(FenParseError::EnPassant)?;
+        let en_passant = if en_pass_square == "-" {
+            Square::NO_SQUARE
         } else {
-            en_passant = match Square::from_alg(en_pass_square) {
+            match Square::from_alg(en_pass_square) {
                 Some(square) => square,
                 None => return Err(FenParseError::EnPassant)
             }
+        };
+
+        // the square a pawn just skipped, empty and with the pawn in front
+        // of it and nothing on the square it came from
+        if en_passant != Square::NO_SQUARE {
+            let occupied = bitboards.iter().fold(0u64, |all, bb| all | bb.to_int());
+            let index = en_passant.to_int();
+            let (rank, pawns, pawn, origin) = match to_move {
+                Color::WHITE => (6, bitboards[11], index + 8, index.wrapping_sub(8)),
+                Color::BLACK => (3, bitboards[5], index.wrapping_sub(8), index + 8),
+            };
+            if en_passant.rank() != rank ||
+               occupied & (1u64 << index | 1u64 << origin) != 0 ||
+               pawns.get_bit(pawn) == 0 {
+                return Err(FenParseError::EnPassant);
+            }
         }
 
-        match fen
+        let halfmove_clock = match fen
             .split_whitespace()
             .nth(4)
-            .unwrap()
-            .parse::<u8>() {
+            .ok_or(FenParseError::HalfMove)?
+            .parse::<u16>() {
 
             Ok(inner) => inner,
             Err(_) => {
@@ -161,15 +270,17 @@ impl Board {
 
         };
 
-        match fen.split_whitespace().nth(5).unwrap().parse::<u64>() {
+        let fullmove_number = match fen
+            .split_whitespace()
+            .nth(5)
+            .ok_or(FenParseError::Ply)?
+            .parse::<u16>() {
+
             Ok(inner) => {
                 if inner == 0 {
                     return Err(FenParseError::Ply);
                 }
-                match to_move {
-                    Color::WHITE => inner*2-1,
-                    Color::BLACK => inner*2
-                }
+                inner
             }
             Err(_) => {
                 return Err(FenParseError::Ply);
@@ -177,14 +288,80 @@ impl Board {
         };
 
         // set fields
-        Ok(Board {
+        let mut board = Board {
             bitboards,
             to_move,
             castling_rights,
             en_passant,
             in_check: false,
+            halfmove_clock,
+            fullmove_number,
+            hash: 0,
+            history: Vec::new(),
             pseudo_moves: MoveList::empty(),
-        })
+        };
+        board.hash = board.gen_hash();
+        board.in_check = board.is_attacked(
+            board.king_square(to_move),
+            Some(!to_move));
+
+        // otherwise the side to move could capture the king
+        if board.is_attacked(board.king_square(!to_move), Some(to_move)) {
+            return Err(FenParseError::OpponentInCheck);
+        }
+        Ok(board)
+    }
+
+    /// Get the FEN string of the position, with all six fields.
+    pub fn to_fen(&self) -> String {
+        let chars = ['K', 'Q', 'R', 'B', 'N', 'P', 'k', 'q', 'r', 'b', 'n', 'p'];
+        let mut fen = String::new();
+
+        for rank in 0..8 {
+            let mut empty = 0;
+            for file in 0..8 {
+                let square = Square::new(rank * 8 + file);
+                match self.get_bitboard_index_at_square(square) {
+                    Some(index) => {
+                        if empty > 0 {
+                            fen += &empty.to_string();
+                            empty = 0;
+                        }
+                        fen.push(chars[index]);
+                    },
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen += &empty.to_string();
+            }
+            if rank < 7 {
+                fen.push('/');
+            }
+        }
+
+        fen += match self.to_move {
+            Color::WHITE => " w ",
+            Color::BLACK => " b ",
+        };
+
+        if self.castling_rights == 0 {
+            fen.push('-');
+        }
+        for (bit, c) in [(8, 'K'), (4, 'Q'), (2, 'k'), (1, 'q')] {
+            if self.castling_rights & bit != 0 {
+                fen.push(c);
+            }
+        }
+
+        if self.en_passant == Square::NO_SQUARE {
+            fen += " -";
+        } else {
+            fen += &format!(" {}", self.en_passant);
+        }
+
+        fen += &format!(" {} {}", self.halfmove_clock, self.fullmove_number);
+        fen
     }
 
     /// Creates a new board from the starting position.
@@ -202,6 +379,92 @@ impl Board {
         self.to_move
     }
 
+    /// Get Zobrist hash of the current position.
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Compute the Zobrist hash of the position from scratch.
+    #[allow(clippy::while_let_loop)]
+    pub fn gen_hash(&self) -> u64 {
+        let mut hash = 0u64;
+        for (i, bb) in self.bitboards.iter().enumerate() {
+            let mut bb = *bb;
+            loop {
+                let square = match bb.get_lsb_square() {
+                    Some(sq) => sq,
+                    None => break,
+                };
+                hash ^= piece_key(i, square);
+                bb.pop_bit_by_square(square);
+            }
+        }
+        hash ^= castling_key(self.castling_rights);
+        hash ^= en_passant_key(self.en_passant);
+        if let Color::BLACK = self.to_move {
+            hash ^= side_key();
+        }
+        hash
+    }
+
+    /// Get number of halfmoves since the last capture or pawn move.
+    pub fn get_halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /// Get the number of the current full move, starting at 1 and going up
+    /// after every black move.
+    pub fn get_fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    /// Number of times the current position occurred before. Only positions
+    /// since the last capture or pawn move can repeat, with the same side to
+    /// move every other ply.
+    pub fn repetitions(&self) -> usize {
+        self.history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .skip(1)
+            .step_by(2)
+            .filter(|&&hash| hash == self.hash)
+            .count()
+    }
+
+    /// Whether the position occurred for the third time.
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    /// Whether fifty moves by each side passed without a capture or pawn
+    /// move. Checkmate on the last move still counts as checkmate.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100 &&
+            !(self.in_check && self.legal_moves().is_empty())
+    }
+
+    /// Whether neither side has the material left to ever give mate: bare
+    /// kings, a single minor piece, or only bishops all on one square color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = self.bitboards[1] | self.bitboards[2] | self.bitboards[5] |
+                    self.bitboards[7] | self.bitboards[8] | self.bitboards[11];
+        if !heavy.is_empty() {
+            return false;
+        }
+
+        let knights = self.bitboards[4] | self.bitboards[10];
+        let bishops = self.bitboards[3] | self.bitboards[9];
+        if (knights | bishops).count_bits() <= 1 {
+            return true;
+        }
+        knights.is_empty() && (
+            (bishops & Bitboard::new(LIGHT_SQUARES)).is_empty() ||
+            (bishops & !Bitboard::new(LIGHT_SQUARES)).is_empty()
+        )
+    }
+
     /// Get whether the king of the current to move color is in check.
     pub fn is_in_check(&self) -> bool {
         self.in_check
@@ -398,6 +661,283 @@ impl Board {
         bb
     }
 
+    /// Get bitboard of all pieces of `color` attacking `square`, with sliding
+    /// pieces blocked by `occupied`.
+    pub fn attackers_to(
+        &self,
+        square: Square,
+        color: Color,
+        occupied: Bitboard,
+    ) -> Bitboard {
+        let (pieces, pawn_side) = match color {
+            Color::WHITE => (&self.bitboards[..6], 1),
+            Color::BLACK => (&self.bitboards[6..], 0),
+        };
+        let queens = pieces[1];
+
+        let mut attackers = EMPTY;
+        unsafe {
+            attackers |= PAWN_ATTACKS[pawn_side][square.to_index()] & pieces[5];
+            attackers |= KNIGHT_ATTACKS[square.to_index()] & pieces[4];
+            attackers |= KING_ATTACKS[square.to_index()] & pieces[0];
+        }
+        attackers |= get_bishop_rays(square, occupied) & (pieces[3] | queens);
+        attackers |= get_rook_rays(square, occupied) & (pieces[2] | queens);
+        attackers
+    }
+
+    /// Get bitboard of pieces of `color` pinned to their own king.
+    #[allow(clippy::while_let_loop)]
+    pub fn get_pinned(&self, color: Color) -> Bitboard {
+        let king = self.king_square(color);
+        let (ours, theirs, offset) = match color {
+            Color::WHITE => (self.get_white_pieces(), self.get_black_pieces(), 6),
+            Color::BLACK => (self.get_black_pieces(), self.get_white_pieces(), 0),
+        };
+        let queens = self.bitboards[offset + 1];
+        let occupied = ours | theirs;
+
+        // enemy sliders that would hit the king if our pieces were removed
+        let mut snipers =
+            (get_rook_rays(king, theirs) & (self.bitboards[offset + 2] | queens)) |
+            (get_bishop_rays(king, theirs) & (self.bitboards[offset + 3] | queens));
+
+        let mut pinned = EMPTY;
+        loop {
+            let sniper = match snipers.get_lsb_square() {
+                Some(sq) => sq,
+                None => break,
+            };
+            let blockers = unsafe {
+                BETWEEN[king.to_index()][sniper.to_index()] & occupied
+            };
+            if blockers.count_bits() == 1 && !(blockers & ours).is_empty() {
+                pinned |= blockers;
+            }
+            snipers.pop_bit_by_square(sniper);
+        }
+        pinned
+    }
+
+    /// Generate strictly legal moves for the side to move. An empty list
+    /// means checkmate if in check, stalemate otherwise.
+    pub fn legal_moves(&self) -> MoveList {
+        let us = self.to_move;
+        let them = !us;
+        let king = self.king_square(us);
+        let occupied = self.get_all_pieces();
+
+        if !self.attackers_to(king, them, occupied).is_empty() {
+            return self.evasions();
+        }
+        let pinned = self.get_pinned(us);
+
+        let mut moves = MoveList::empty();
+        for &mv in self.pseudo_legal_moves().iter() {
+            let origin = mv.get_origin();
+            let target = mv.get_target();
+
+            if origin == king {
+                if !self.attackers_to(target, them, occupied).is_empty() {
+                    continue;
+                }
+                match mv.get_special() {
+                    SpecialMove::KingsideCastle | SpecialMove::QueensideCastle => {
+                        let passed = unsafe {
+                            BETWEEN[king.to_index()][target.to_index()]
+                                .get_lsb_square()
+                                .unwrap()
+                        };
+                        if !self.attackers_to(passed, them, occupied).is_empty() {
+                            continue;
+                        }
+                    },
+                    _ => {}
+                }
+                moves.add(mv);
+                continue;
+            }
+
+            // en passant removes two pieces from a line, just test it
+            if let SpecialMove::EnPassant = mv.get_special() {
+                let victim = Bitboard::from_square(self.en_passant_victim(target));
+                let after = (occupied ^ Bitboard::from_square(origin) ^ victim) |
+                            Bitboard::from_square(target);
+                if (self.attackers_to(king, them, after) & !victim).is_empty() {
+                    moves.add(mv);
+                }
+                continue;
+            }
+
+            if pinned.get_bit_by_square(origin) == 1 && unsafe {
+                LINE[king.to_index()][origin.to_index()]
+                    .get_bit_by_square(target) == 0
+            } {
+                continue;
+            }
+            moves.add(mv);
+        }
+        moves
+    }
+
+    /// Get the legal moves when the side to move is in check, generated from
+    /// the checking pieces: king steps, captures of a single checker and
+    /// blocks. Empty when not in check.
+    #[allow(clippy::while_let_loop)]
+    pub fn evasions(&self) -> MoveList {
+        let us = self.to_move;
+        let them = !us;
+        let king = self.king_square(us);
+        let king_bb = Bitboard::from_square(king);
+        let occupied = self.get_all_pieces();
+
+        let (ours, theirs, offset) = match us {
+            Color::WHITE => (self.get_white_pieces(), self.get_black_pieces(), 0),
+            Color::BLACK => (self.get_black_pieces(), self.get_white_pieces(), 6),
+        };
+
+        let mut moves = MoveList::empty();
+        let checkers = self.attackers_to(king, them, occupied);
+        if checkers.is_empty() {
+            return moves;
+        }
+
+        // king steps, looking through the king so it can't step back along a
+        // checking ray
+        let mut steps = unsafe { KING_ATTACKS[king.to_index()] } & !ours;
+        loop {
+            let target = match steps.get_lsb_square() {
+                Some(sq) => sq,
+                None => break
+            };
+            if self.attackers_to(target, them, occupied ^ king_bb).is_empty() {
+                let special = if theirs.get_bit_by_square(target) == 1 {
+                    SpecialMove::Capture
+                } else {
+                    SpecialMove::Quiet
+                };
+                moves.add(Move::new(king, target, special));
+            }
+            steps.pop_bit_by_square(target);
+        }
+
+        // only the king can answer a double check
+        if checkers.count_bits() > 1 {
+            return moves;
+        }
+
+        let checker = checkers.get_lsb_square().unwrap();
+        let blocks = unsafe { BETWEEN[king.to_index()][checker.to_index()] };
+        let check_mask = checkers | blocks;
+
+        // a pinned piece can't leave the pin line, which never crosses the
+        // check line
+        let movable = ours & !self.get_pinned(us);
+
+        // pawns
+        let (pawn_side, last_rank, start_rank) = match us {
+            Color::WHITE => (0, 8, 2),
+            Color::BLACK => (1, 1, 7),
+        };
+        let promotions = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+        let mut pawns = self.bitboards[offset + 5] & movable;
+        loop {
+            let origin = match pawns.get_lsb_square() {
+                Some(sq) => sq,
+                None => break
+            };
+            let forward = |sq: Square| match us {
+                Color::WHITE => sq.due_n(),
+                Color::BLACK => sq.due_s(),
+            };
+
+            // pushes onto the check line
+            if let Some(push) = forward(origin) {
+                if occupied.get_bit_by_square(push) == 0 {
+                    if blocks.get_bit_by_square(push) == 1 {
+                        if push.rank() == last_rank {
+                            for piece in promotions {
+                                moves.add(Move::new(origin, push,
+                                    SpecialMove::Promotion(piece)));
+                            }
+                        } else {
+                            moves.add(Move::new(origin, push, SpecialMove::Quiet));
+                        }
+                    }
+                    if origin.rank() == start_rank {
+                        if let Some(double) = forward(push) {
+                            if occupied.get_bit_by_square(double) == 0 &&
+                               blocks.get_bit_by_square(double) == 1 {
+                                moves.add(Move::new(origin, double,
+                                    SpecialMove::DoublePush));
+                            }
+                        }
+                    }
+                }
+            }
+
+            let attacks = unsafe { PAWN_ATTACKS[pawn_side][origin.to_index()] };
+
+            // capture the checker
+            if attacks.get_bit_by_square(checker) == 1 {
+                if checker.rank() == last_rank {
+                    for piece in promotions {
+                        moves.add(Move::new(origin, checker,
+                            SpecialMove::PromotionCapture(piece)));
+                    }
+                } else {
+                    moves.add(Move::new(origin, checker, SpecialMove::Capture));
+                }
+            }
+
+            // en passant removes two pieces from a line, just test it
+            if self.en_passant != Square::NO_SQUARE &&
+               attacks.get_bit_by_square(self.en_passant) == 1 {
+                let victim = Bitboard::from_square(self.en_passant_victim(self.en_passant));
+                let after = (occupied ^ Bitboard::from_square(origin) ^ victim) |
+                            Bitboard::from_square(self.en_passant);
+                if (self.attackers_to(king, them, after) & !victim).is_empty() {
+                    moves.add(Move::new(origin, self.en_passant, SpecialMove::EnPassant));
+                }
+            }
+            pawns.pop_bit_by_square(origin);
+        }
+
+        // pieces
+        for piece in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+            let mut bb = self.bitboards[offset + piece.to_index()] & movable;
+            loop {
+                let origin = match bb.get_lsb_square() {
+                    Some(sq) => sq,
+                    None => break
+                };
+                let mut targets = match piece {
+                    Piece::Knight => unsafe { KNIGHT_ATTACKS[origin.to_index()] },
+                    Piece::Bishop => get_bishop_rays(origin, occupied),
+                    Piece::Rook => get_rook_rays(origin, occupied),
+                    _ => get_queen_rays(origin, occupied),
+                } & check_mask;
+
+                loop {
+                    let target = match targets.get_lsb_square() {
+                        Some(sq) => sq,
+                        None => break
+                    };
+                    let special = if target == checker {
+                        SpecialMove::Capture
+                    } else {
+                        SpecialMove::Quiet
+                    };
+                    moves.add(Move::new(origin, target, special));
+                    targets.pop_bit_by_square(target);
+                }
+                bb.pop_bit_by_square(origin);
+            }
+        }
+
+        moves
+    }
+
     /// Get the bitboard index at square.
     pub fn get_bitboard_index_at_square(&self, square: Square) -> Option<usize> {
         for (i, bb) in self.bitboards.iter().enumerate() {
@@ -415,13 +955,19 @@ impl Board {
             Some(p) => p
         };
         self.bitboards[i].pop_bit_by_square(square);
+        self.hash ^= piece_key(i, square);
     } 
 
-    /// Generate pseudo legal moves.
+    /// Generate pseudo legal moves into `pseudo_moves`.
     pub fn gen_moves(&mut self) {
-        // TODO: ignore all moves that don't block/capture checking pieces or move king.
-        // reset move_list
-        self.pseudo_moves = MoveList::empty();
+        self.pseudo_moves = self.pseudo_legal_moves();
+    }
+
+    /// Get pseudo legal moves. These may still leave the king in check, use
+    /// `legal_moves` when that matters.
+    #[allow(clippy::clone_on_copy, clippy::collapsible_if, clippy::while_let_loop)]
+    pub fn pseudo_legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::empty();
 
         let all_pieces = self.get_all_pieces();
         let white_pieces = self.get_white_pieces();
@@ -436,32 +982,32 @@ impl Board {
                         Some(sq) => sq,
                         None => break
                     };
-                    let target = Square::new((origin.to_int() - 8) as u8);
+                    let target = Square::new(origin.to_int() - 8);
                     
                     if all_pieces.get_bit_by_square(target) == 0 {
                         if origin.rank() == 7 {
                             // quiet promotions
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Queen)));
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Rook)));
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Bishop)));
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Knight)));
                         } else {
                             // quiet push
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target,
                                      SpecialMove::Quiet));
                             if origin.rank() == 2 &&
-                                all_pieces.get_bit(origin.to_int() as u8 - 16) != 1 {
+                                all_pieces.get_bit(origin.to_int() - 16) != 1 {
                                     // double push
-                                    self.pseudo_moves
+                                    moves
                                         .add(Move::new(origin,
                                             Square::new(target.to_int() - 8),
                                             SpecialMove::DoublePush));
@@ -475,7 +1021,7 @@ impl Board {
                         // en passant
                         if self.en_passant != Square::NO_SQUARE && 
                         attacks.get_bit_by_square(self.en_passant) == 1 {
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, self.en_passant,
                                      SpecialMove::EnPassant))  
                         }
@@ -488,25 +1034,25 @@ impl Board {
                             };
                             if target.rank() == 8 {
                                 // capture promotion
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
                                         SpecialMove::
                                         PromotionCapture(Piece::Queen)));
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
                                         SpecialMove::
                                         PromotionCapture(Piece::Rook)));
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
                                         SpecialMove::
                                         PromotionCapture(Piece::Bishop)));
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
@@ -514,7 +1060,7 @@ impl Board {
                                         PromotionCapture(Piece::Knight)));
                             } else {
                                 // capture
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
@@ -546,14 +1092,14 @@ impl Board {
                             };
                             if black_pieces.get_bit_by_square(target) == 1 {
                                 // capture
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin,
                                         target,
                                         SpecialMove::Capture))
                             } else {
                                 // quiet
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin,
                                         target,
@@ -581,14 +1127,14 @@ impl Board {
                         };
                         if black_pieces.get_bit_by_square(target) == 1 {
                             // capture
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
                                     SpecialMove::Capture))
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -605,7 +1151,7 @@ impl Board {
                     if (all_pieces & between).is_empty() {
                         if !self.is_attacked(Square::F1, Some(Color::BLACK)) &&
                         !self.in_check {
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     Square::new(origin.to_int() + 2),
@@ -623,7 +1169,7 @@ impl Board {
                     if (all_pieces & between).is_empty() {
                         if !self.is_attacked(Square::D1, Some(Color::BLACK)) &&
                         !self.in_check {
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     Square::new(origin.to_int() - 2),
@@ -651,14 +1197,14 @@ impl Board {
                         };
                         if black_pieces.get_bit_by_square(target) == 1 {
                             // capture
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
                                     SpecialMove::Capture))
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -688,14 +1234,14 @@ impl Board {
                         };
                         if black_pieces.get_bit_by_square(target) == 1 {
                            // capture
-                           self.pseudo_moves
+                           moves
                            .add(Move::new(
                                origin,
                                target,
                                SpecialMove::Capture)) 
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -724,14 +1270,14 @@ impl Board {
                         };
                         if black_pieces.get_bit_by_square(target) == 1 {
                            // capture
-                           self.pseudo_moves
+                           moves
                            .add(Move::new(
                                origin,
                                target,
                                SpecialMove::Capture)) 
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -750,32 +1296,32 @@ impl Board {
                         Some(sq) => sq,
                         None => break
                     };
-                    let target = Square::new((origin.to_int() + 8) as u8);
+                    let target = Square::new(origin.to_int() + 8);
                     
                     if all_pieces.get_bit_by_square(target) == 0 {
                         if origin.rank() == 2 {
                             // quiet promotions
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Queen)));
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Rook)));
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Bishop)));
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target, 
                                      SpecialMove::Promotion(Piece::Knight)));
                         } else {
                             // quiet push
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, target,
                                      SpecialMove::Quiet));
                             if origin.rank() == 7 &&
-                            all_pieces.get_bit(origin.to_int() as u8 + 16) != 1 {
+                            all_pieces.get_bit(origin.to_int() + 16) != 1 {
                                     // double push
-                                    self.pseudo_moves
+                                    moves
                                         .add(Move::new(origin,
                                             Square::new(target.to_int() + 8),
                                             SpecialMove::DoublePush));
@@ -789,7 +1335,7 @@ impl Board {
                         // en passant
                         if self.en_passant != Square::NO_SQUARE && 
                         attacks.get_bit_by_square(self.en_passant) == 1 {
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(origin, self.en_passant,
                                      SpecialMove::EnPassant))  
                         }
@@ -802,25 +1348,25 @@ impl Board {
                             };
                             if target.rank() == 1 {
                                 // capture promotion
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
                                         SpecialMove::
                                         PromotionCapture(Piece::Queen)));
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
                                         SpecialMove::
                                         PromotionCapture(Piece::Rook)));
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
                                         SpecialMove::
                                         PromotionCapture(Piece::Bishop)));
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
@@ -828,7 +1374,7 @@ impl Board {
                                         PromotionCapture(Piece::Knight)));
                             } else {
                                 // capture
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin, 
                                         target, 
@@ -860,14 +1406,14 @@ impl Board {
                             };
                             if white_pieces.get_bit_by_square(target) == 1 {
                                 // capture
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin,
                                         target,
                                         SpecialMove::Capture))
                             } else {
                                 // quiet
-                                self.pseudo_moves
+                                moves
                                     .add(Move::new(
                                         origin,
                                         target,
@@ -895,14 +1441,14 @@ impl Board {
                         };
                         if white_pieces.get_bit_by_square(target) == 1 {
                             // capture
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
                                     SpecialMove::Capture))
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -919,7 +1465,7 @@ impl Board {
                     if (all_pieces & between).is_empty() {
                         if !self.is_attacked(Square::F8, Some(Color::WHITE)) &&
                         !self.in_check {
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     Square::new(origin.to_int() + 2),
@@ -937,7 +1483,7 @@ impl Board {
                     if (all_pieces & between).is_empty() {
                         if !self.is_attacked(Square::D8, Some(Color::WHITE)) &&
                         !self.in_check {
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     Square::new(origin.to_int() - 2),
@@ -965,14 +1511,14 @@ impl Board {
                         };
                         if white_pieces.get_bit_by_square(target) == 1 {
                             // capture
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
                                     SpecialMove::Capture))
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -1002,14 +1548,14 @@ impl Board {
                         };
                         if white_pieces.get_bit_by_square(target) == 1 {
                            // capture
-                           self.pseudo_moves
+                           moves
                            .add(Move::new(
                                origin,
                                target,
                                SpecialMove::Capture)) 
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -1038,14 +1584,14 @@ impl Board {
                         };
                         if white_pieces.get_bit_by_square(target) == 1 {
                            // capture
-                           self.pseudo_moves
+                           moves
                            .add(Move::new(
                                origin,
                                target,
                                SpecialMove::Capture)) 
                         } else {
                             // quiet
-                            self.pseudo_moves
+                            moves
                                 .add(Move::new(
                                     origin,
                                     target,
@@ -1057,273 +1603,446 @@ impl Board {
                 }
             },
         }
-    }
 
-    /// Make a move and return if it is valid.
-    pub fn make_move(&mut self, mv: Move) -> bool {
+        moves
+    }
 
-        let origin = mv.get_origin();
-        let target = mv.get_target();
+    /// Get pseudo legal captures, en passant captures and promotions. Used by
+    /// the quiescence search, which only looks at moves that change material.
+    #[allow(clippy::while_let_loop)]
+    pub fn pseudo_legal_captures(&self) -> MoveList {
+        let mut moves = MoveList::empty();
 
-        // update castling rights if rooks are taken
-        match self.get_bitboard_index_at_square(target) {
-            Some(i) => {
-                if i == 2 {
-                    if target == Square::A1 {
-                        self.castling_rights &= 0b1011;        
-                    } else if target == Square::H1 {
-                        self.castling_rights &= 0b0111;        
-                    }
-                } else if i == 8 {
-                    if target == Square::A8 {
-                        self.castling_rights &= 0b1110;        
-                    } else if origin == Square::H8 {
-                        self.castling_rights &= 0b1101;        
+        let (ours, theirs, offset, pawn_side, last_rank) = match self.to_move {
+            Color::WHITE => (self.get_white_pieces(), self.get_black_pieces(), 0, 0, 8),
+            Color::BLACK => (self.get_black_pieces(), self.get_white_pieces(), 6, 1, 1),
+        };
+        let occupied = ours | theirs;
+        let promotions = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+
+        // pawns
+        let mut bb = self.bitboards[offset + 5];
+        loop {
+            let origin = match bb.get_lsb_square() {
+                Some(sq) => sq,
+                None => break
+            };
+
+            // quiet promotions
+            let push = match self.to_move {
+                Color::WHITE => origin.due_n(),
+                Color::BLACK => origin.due_s(),
+            };
+            if let Some(target) = push {
+                if target.rank() == last_rank &&
+                   occupied.get_bit_by_square(target) == 0 {
+                    for piece in promotions {
+                        moves.add(Move::new(origin, target,
+                            SpecialMove::Promotion(piece)));
                     }
                 }
-            },
-            None => {}
-        };
+            }
 
-        // handle special moves.
-        let mut new_en_passant = false;
-        match mv.get_special() {
-            SpecialMove::Quiet => {
-                self.en_passant = Square::NO_SQUARE;
-            },
-            SpecialMove::DoublePush => {
-                match self.to_move {
-                    Color::WHITE => {
-                        self.en_passant = origin.due_n().unwrap();
-                    },
-                    Color::BLACK => {
-                        self.en_passant = origin.due_s().unwrap();
+            let attacks = unsafe { PAWN_ATTACKS[pawn_side][origin.to_index()] };
+
+            // en passant
+            if self.en_passant != Square::NO_SQUARE &&
+               attacks.get_bit_by_square(self.en_passant) == 1 {
+                moves.add(Move::new(origin, self.en_passant,
+                    SpecialMove::EnPassant));
+            }
+
+            let mut attacks = attacks & theirs;
+            loop {
+                let target = match attacks.get_lsb_square() {
+                    Some(sq) => sq,
+                    None => break
+                };
+                if target.rank() == last_rank {
+                    for piece in promotions {
+                        moves.add(Move::new(origin, target,
+                            SpecialMove::PromotionCapture(piece)));
                     }
+                } else {
+                    moves.add(Move::new(origin, target, SpecialMove::Capture));
                 }
-                new_en_passant = true;
-            },
-            SpecialMove::KingsideCastle => {
-                // update castling rights
-                match self.to_move {
-                    Color::WHITE => {
-                        self.castling_rights &= 0b0011;
-
-                        // move rook
-                        self.bitboards[2]
-                            .move_bit_by_square(Square::H1, Square::F1);
-                    },
-                    Color::BLACK => {
-                        self.castling_rights &= 0b1100;
+                attacks.pop_bit_by_square(target);
+            }
+            bb.pop_bit_by_square(origin);
+        }
 
-                        // move rook
-                        self.bitboards[8]
-                            .move_bit_by_square(Square::H8, Square::F8);
-                    },
-                }
-            },
-            SpecialMove::QueensideCastle => {
-                // update castling rights
-                match self.to_move {
-                    Color::WHITE => {
-                        self.castling_rights &= 0b0011;
-
-                        // move rook
-                        self.bitboards[2]
-                            .move_bit_by_square(Square::A1, Square::D1);
-                    },
-                    Color::BLACK => {
-                        self.castling_rights &= 0b1100;
+        // pieces
+        for piece in [Piece::King, Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+            let mut bb = self.bitboards[offset + piece.to_index()];
+            loop {
+                let origin = match bb.get_lsb_square() {
+                    Some(sq) => sq,
+                    None => break
+                };
+                let mut attacks = match piece {
+                    Piece::King => unsafe { KING_ATTACKS[origin.to_index()] },
+                    Piece::Knight => unsafe { KNIGHT_ATTACKS[origin.to_index()] },
+                    Piece::Bishop => get_bishop_rays(origin, occupied),
+                    Piece::Rook => get_rook_rays(origin, occupied),
+                    _ => get_queen_rays(origin, occupied),
+                } & theirs;
 
-                        // move rook
-                        self.bitboards[8]
-                            .move_bit_by_square(Square::A8, Square::D8);
-                    },
+                loop {
+                    let target = match attacks.get_lsb_square() {
+                        Some(sq) => sq,
+                        None => break
+                    };
+                    moves.add(Move::new(origin, target, SpecialMove::Capture));
+                    attacks.pop_bit_by_square(target);
                 }
-            },
-            SpecialMove::Capture => {
-                self.del_piece(target);
-            },
+                bb.pop_bit_by_square(origin);
+            }
+        }
+
+        moves
+    }
+
+    /// Whether `mv` is one of the pseudo legal moves of this position. Used to
+    /// check moves that come from elsewhere, like the transposition table.
+    pub fn is_pseudo_legal(&self, mv: Move) -> bool {
+        if mv.is_capture() || mv.is_promotion() {
+            self.pseudo_legal_captures().contains(mv)
+        } else {
+            self.pseudo_legal_moves().contains(mv)
+        }
+    }
+
+    /// Static exchange evaluation: material won by the side to move when both
+    /// sides keep recapturing on the target square of `mv` with their least
+    /// valuable piece, each free to stop when recapturing would lose.
+    pub fn see(&self, mv: Move) -> i32 {
+        let origin = mv.get_origin();
+        let target = mv.get_target();
+        let special = mv.get_special();
+
+        let mut occupied = self.get_all_pieces();
+        occupied.pop_bit_by_square(origin);
+
+        // material of the first capture and the piece left standing on target
+        let mut gain = [0i32; 32];
+        gain[0] = match special {
             SpecialMove::EnPassant => {
-                match self.to_move {
-                    Color::WHITE => {
-                        self.bitboards[11]
-                            .pop_bit_by_square(target.due_s().unwrap());
-                    },
-                    Color::BLACK => {
-                        self.bitboards[5]
-                            .pop_bit_by_square(target.due_n().unwrap());
-                    }
-                }
+                occupied.pop_bit_by_square(self.en_passant_victim(target));
+                piece_value(5)
             },
-            SpecialMove::Promotion(piece) => {
-                self.del_piece(origin);
-                match self.to_move {
-                    Color::WHITE => {
-                        // remove pawn
-                        self.bitboards[5].pop_bit_by_square(origin);
-
-                        // add new piece
-                        match piece {
-                            Piece::Queen => {
-                                self.bitboards[1].set_bit_by_square(target);
-                            },
-                            Piece::Rook => {
-                                self.bitboards[2].set_bit_by_square(target);
-                            }
-                            Piece::Bishop => {
-                                self.bitboards[3].set_bit_by_square(target);
-                            }
-                            Piece::Knight => {
-                                self.bitboards[4].set_bit_by_square(target);
-                            }
-                            _ => return false,
-                        }
-                    },
-                    Color::BLACK => {
-                        // remove pawn
-                        self.bitboards[11].pop_bit_by_square(target);
-
-                        // add new piece
-                        match piece {
-                            Piece::Queen => {
-                                self.bitboards[7].set_bit_by_square(target);
-                            },
-                            Piece::Rook => {
-                                self.bitboards[8].set_bit_by_square(target);
-                            }
-                            Piece::Bishop => {
-                                self.bitboards[9].set_bit_by_square(target);
-                            }
-                            Piece::Knight => {
-                                self.bitboards[10].set_bit_by_square(target);
-                            }
-                            _ => return false,
-                        }
-                    }
-                }
+            _ => match self.get_bitboard_index_at_square(target) {
+                Some(index) => piece_value(index),
+                None => 0,
             },
-            SpecialMove::PromotionCapture(piece) => {
-                self.del_piece(target);
-                self.del_piece(origin);
-                match self.to_move {
-                    Color::WHITE => {
-                        // add new piece
-                        match piece {
-                            Piece::Queen => {
-                                self.bitboards[1].set_bit_by_square(target);
-                            },
-                            Piece::Rook => {
-                                self.bitboards[2].set_bit_by_square(target);
-                            }
-                            Piece::Bishop => {
-                                self.bitboards[3].set_bit_by_square(target);
-                            }
-                            Piece::Knight => {
-                                self.bitboards[4].set_bit_by_square(target);
-                            }
-                            _ => return false,
-                        }
-                    },
-                    Color::BLACK => {
-                        // add new piece
-                        match piece {
-                            Piece::Queen => {
-                                self.bitboards[7].set_bit_by_square(target);
-                            },
-                            Piece::Rook => {
-                                self.bitboards[8].set_bit_by_square(target);
-                            }
-                            Piece::Bishop => {
-                                self.bitboards[9].set_bit_by_square(target);
-                            }
-                            Piece::Knight => {
-                                self.bitboards[10].set_bit_by_square(target);
-                            }
-                            _ => return false,
-                        }
-                    }
-                }
-            }
+        };
+        let mut on_target = match self.get_bitboard_index_at_square(origin) {
+            Some(index) => piece_value(index),
+            None => return 0,
+        };
+        match special {
+            SpecialMove::Promotion(p) | SpecialMove::PromotionCapture(p) => {
+                gain[0] += piece_value(p.to_index()) - piece_value(5);
+                on_target = piece_value(p.to_index());
+            },
+            _ => {},
         }
 
-        // if new en passant square not set, reset the field.
-        if !new_en_passant {self.en_passant = Square::NO_SQUARE}
+        let mut color = !self.to_move;
+        let mut depth = 0;
+        loop {
+            // recomputed every capture so sliders behind the last capturer
+            // join in as x-rays
+            let attackers = (
+                self.attackers_to(target, Color::WHITE, occupied) |
+                self.attackers_to(target, Color::BLACK, occupied)
+            ) & occupied;
+            let (ours, offset) = match color {
+                Color::WHITE => (attackers & self.get_white_pieces(), 0),
+                Color::BLACK => (attackers & self.get_black_pieces(), 6),
+            };
+            if ours.is_empty() || depth + 1 >= gain.len() {
+                break;
+            }
 
-        // Figure out which piece moved and move it only if its not a promotion.
-        match mv.get_special() {
-            SpecialMove::Promotion(_) =>{},
-            SpecialMove::PromotionCapture(_) => {},
-            _ => {
-                let origin_index = self.get_bitboard_index_at_square(origin)
-                    .unwrap();
-                self.bitboards[origin_index].move_bit_by_square(origin, target);
-
-                // update castling rights if king or rooks are moved
-                if origin_index == 2 { // white rooks
-                    if origin == Square::A1 {
-                        self.castling_rights &= 0b1011;        
-                    } else if origin == Square::H1 { 
-                        self.castling_rights &= 0b0111;        
-                    }
-                } else if origin_index == 8 { // black rooks
-                    if origin == Square::A8 {
-                        self.castling_rights &= 0b1110;        
-                    } else if origin == Square::H8 {
-                        self.castling_rights &= 0b1101;        
-                    }
-                } else if origin_index == 0 { // white king
-                    self.castling_rights &= 0b0011;
-                } else if origin_index == 6 { // black king
-                    self.castling_rights &= 0b1100;
+            // least valuable attacker
+            let mut capturer = None;
+            for index in [5, 4, 3, 2, 1, 0] {
+                if let Some(sq) = (ours & self.bitboards[offset + index]).get_lsb_square() {
+                    capturer = Some((sq, index));
+                    break;
                 }
             }
+            let (square, index) = capturer.unwrap();
+
+            // the king can only take when nothing recaptures
+            if index == 0 && !(attackers & !ours).is_empty() {
+                break;
+            }
+
+            depth += 1;
+            gain[depth] = on_target - gain[depth - 1];
+            on_target = piece_value(index);
+            occupied.pop_bit_by_square(square);
+            color = !color;
         }
 
+        // each side picks the better of capturing and standing pat
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
 
+    /// Whether the static exchange evaluation of `mv` is at least `threshold`.
+    #[inline]
+    pub fn see_ge(&self, mv: Move, threshold: i32) -> bool {
+        self.see(mv) >= threshold
+    }
+
+    /// Get the bitboard index of `piece` for the side to move.
+    fn piece_index(&self, piece: Piece) -> usize {
         match self.to_move {
-            Color::WHITE => {
-                self.in_check = 
-                    self.is_attacked(
-                        self.bitboards[6]
-                            .get_lsb_square()
-                            .unwrap(),
-                        Some(Color::WHITE))
+            Color::WHITE => piece.to_index(),
+            Color::BLACK => piece.to_index() + 6,
+        }
+    }
+
+    /// Square of the pawn taken by an en passant capture landing on `target`
+    /// for the side to move.
+    fn en_passant_victim(&self, target: Square) -> Square {
+        match self.to_move {
+            Color::WHITE => target.due_s().unwrap(),
+            Color::BLACK => target.due_n().unwrap(),
+        }
+    }
+
+    /// Rook origin and target squares for a castling move by the side to move.
+    fn castling_rook_squares(&self, special: &SpecialMove) -> Option<(Square, Square)> {
+        match (special, self.to_move) {
+            (SpecialMove::KingsideCastle, Color::WHITE)  => Some((Square::H1, Square::F1)),
+            (SpecialMove::KingsideCastle, Color::BLACK)  => Some((Square::H8, Square::F8)),
+            (SpecialMove::QueensideCastle, Color::WHITE) => Some((Square::A1, Square::D1)),
+            (SpecialMove::QueensideCastle, Color::BLACK) => Some((Square::A8, Square::D8)),
+            _ => None,
+        }
+    }
+
+    /// Make a move. Returns the record needed to take it back with
+    /// `unmake_move`, or `None` if the move would leave the mover's king in
+    /// check, in which case the board is left unchanged.
+    pub fn make_move(&mut self, mv: Move) -> Option<Undo> {
+
+        let origin = mv.get_origin();
+        let target = mv.get_target();
+        let special = mv.get_special();
+
+        let moving = self.get_bitboard_index_at_square(origin)?;
+
+        let mut undo = Undo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+            in_check: self.in_check,
+        };
+        self.history.push(self.hash);
+
+        // hash out the old castling rights and en passant square, they are
+        // hashed back in once the move is done
+        self.hash ^= castling_key(self.castling_rights);
+        self.hash ^= en_passant_key(self.en_passant);
+
+        // remove captured piece
+        match special {
+            SpecialMove::Capture | SpecialMove::PromotionCapture(_) => {
+                undo.captured = self.get_bitboard_index_at_square(target);
+                self.del_piece(target);
             },
-            Color::BLACK => {
-                self.in_check = 
-                    self.is_attacked(
-                        self.bitboards[0]
-                            .get_lsb_square()
-                            .unwrap(), 
-                        Some(Color::BLACK))
+            SpecialMove::EnPassant => {
+                let victim = self.en_passant_victim(target);
+                undo.captured = self.get_bitboard_index_at_square(victim);
+                self.del_piece(victim);
             },
+            _ => {}
+        }
+
+        // move the piece, swapping in the new piece for promotions
+        let placed = match special {
+            SpecialMove::Promotion(piece) |
+            SpecialMove::PromotionCapture(piece) => self.piece_index(piece),
+            _ => moving,
+        };
+        self.bitboards[moving].pop_bit_by_square(origin);
+        self.bitboards[placed].set_bit_by_square(target);
+        self.hash ^= piece_key(moving, origin) ^ piece_key(placed, target);
+
+        // move rook when castling
+        if let Some((from, to)) = self.castling_rook_squares(&special) {
+            let rook = self.piece_index(Piece::Rook);
+            self.bitboards[rook].move_bit_by_square(from, to);
+            self.hash ^= piece_key(rook, from) ^ piece_key(rook, to);
+        }
+
+        // moving or capturing on a king or rook square loses those rights
+        self.castling_rights &= CASTLING_MASK[origin.to_index()] &
+                                CASTLING_MASK[target.to_index()];
+
+        self.en_passant = match special {
+            SpecialMove::DoublePush => self.en_passant_victim(target),
+            _ => Square::NO_SQUARE,
+        };
+
+        // pawn moves and captures reset the fifty move counter
+        if moving % 6 == Piece::Pawn.to_index() || undo.captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
         }
 
+        // change color, a new full move starts after black moved
+        if let Color::BLACK = self.to_move {
+            self.fullmove_number += 1;
+        }
+        self.to_move = !self.to_move;
+        self.hash ^= side_key();
+        self.hash ^= castling_key(self.castling_rights);
+        self.hash ^= en_passant_key(self.en_passant);
 
         // does move leave king in check?
-        match self.to_move {
-            Color::WHITE => {
-                if self.is_attacked(
-                    self.bitboards[0].get_lsb_square().unwrap(), 
-                    Some(Color::BLACK)) {
-                    
-                    return false;
-                }
+        if self.is_attacked(self.king_square(!self.to_move), Some(self.to_move)) {
+            self.unmake_move(mv, undo);
+            return None;
+        }
+
+        // is the opponent now in check?
+        self.in_check = self.is_attacked(
+            self.king_square(self.to_move),
+            Some(!self.to_move));
+
+        debug_assert_eq!(
+            self.hash,
+            self.gen_hash(),
+            "incremental hash diverged after {mv}"
+        );
+        Some(undo)
+    }
+
+    /// Take back a move made with `make_move`.
+    pub fn unmake_move(&mut self, mv: Move, undo: Undo) {
+
+        let origin = mv.get_origin();
+        let target = mv.get_target();
+        let special = mv.get_special();
+
+        // change color back to the side that made the move
+        self.to_move = !self.to_move;
+
+        // move the piece back, turning promoted pieces back into pawns
+        let placed = self.get_bitboard_index_at_square(target).unwrap();
+        let moving = match special {
+            SpecialMove::Promotion(_) |
+            SpecialMove::PromotionCapture(_) => {
+                self.piece_index(Piece::Pawn)
             },
-            Color::BLACK => {
-                if self.is_attacked(
-                    self.bitboards[6].get_lsb_square().unwrap(), 
-                    Some(Color::WHITE)) {
+            _ => placed,
+        };
+        self.bitboards[placed].pop_bit_by_square(target);
+        self.bitboards[moving].set_bit_by_square(origin);
 
-                    return false;
-                }
-            }
+        // move rook back when castling
+        if let Some((from, to)) = self.castling_rook_squares(&special) {
+            let rook = self.piece_index(Piece::Rook);
+            self.bitboards[rook].move_bit_by_square(to, from);
+        }
+
+        // put back captured piece
+        if let Some(captured) = undo.captured {
+            let square = match special {
+                SpecialMove::EnPassant => self.en_passant_victim(target),
+                _ => target,
+            };
+            self.bitboards[captured].set_bit_by_square(square);
         }
 
-        // change color
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
+        self.in_check = undo.in_check;
+        self.history.pop();
+    }
+
+    /// Pass the turn without moving, used by null move pruning. Must not be
+    /// called while in check.
+    pub fn make_null_move(&mut self) -> Undo {
+        let undo = Undo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+            in_check: self.in_check,
+        };
+
+        self.history.push(self.hash);
+        self.hash ^= en_passant_key(self.en_passant);
+        self.hash ^= side_key();
+        self.en_passant = Square::NO_SQUARE;
+        // positions before a null move are not part of the same game, so
+        // they can't be repeated after it
+        self.halfmove_clock = 0;
+        if let Color::BLACK = self.to_move {
+            self.fullmove_number += 1;
+        }
         self.to_move = !self.to_move;
-        true
-    } 
+        self.in_check = false;
+
+        debug_assert_eq!(self.hash, self.gen_hash());
+        undo
+    }
+
+    /// Take back a move made with `make_null_move`.
+    pub fn unmake_null_move(&mut self, undo: Undo) {
+        self.to_move = !self.to_move;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
+        self.in_check = undo.in_check;
+        self.history.pop();
+    }
+
+    /// Whether `color` has a piece other than pawns and its king.
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        let offset = match color {
+            Color::WHITE => 0,
+            Color::BLACK => 6,
+        };
+        (1..5).any(|i| !self.bitboards[offset + i].is_empty())
+    }
+
+    /// Get the square of the king of `color`.
+    #[inline]
+    pub fn king_square(&self, color: Color) -> Square {
+        match color {
+            Color::WHITE => self.bitboards[0].get_lsb_square().unwrap(),
+            Color::BLACK => self.bitboards[6].get_lsb_square().unwrap(),
+        }
+    }
+}
+
+impl FromStr for Board {
+    type Err = FenParseError;
+
+    /// Parse a FEN string, the inverse of `Board::to_fen`.
+    fn from_str(fen: &str) -> Result<Board, FenParseError> {
+        Board::new(fen)
+    }
 }
 
 impl fmt::Display for Board {
@@ -1344,10 +2063,12 @@ impl fmt::Display for Board {
         display += "\n\n   a b c d e f g h";
 
         display += &format!(
-            "\n\nto_move = {:?}\ncastling_rights = {:04b}\nen_passant = {}\n",
+            "\n\nto_move = {:?}\ncastling_rights = {:04b}\nen_passant = {}\nhash = {:016x}\nfen = {}\n",
             self.to_move,
             self.castling_rights, 
-            self.en_passant);
+            self.en_passant,
+            self.hash,
+            self.to_fen());
         write! { f, "{}", display }
     }
 }
@@ -0,0 +1,55 @@
+// book.rs
+
+use std::collections::HashMap;
+use crate::board::Board;
+use crate::pgn::{parse_pgn, PgnError};
+use crate::piece_move::Move;
+
+/// Plies of every game that go into the book.
+const BOOK_PLIES: usize = 24;
+
+/// Opening book built from the main lines of a PGN file. Every position of
+/// the first `BOOK_PLIES` plies of a game is stored with the moves played
+/// from it and how often each was played.
+pub struct Book {
+    positions: HashMap<u64, Vec<(Move, u32)>>,
+}
+
+impl Book {
+
+    pub fn from_pgn(text: &str) -> Result<Book, PgnError> {
+        let mut positions: HashMap<u64, Vec<(Move, u32)>> = HashMap::new();
+        for game in parse_pgn(text)? {
+            let mut board = game.start.clone();
+            for mv in game.moves().into_iter().take(BOOK_PLIES) {
+                let moves = positions.entry(board.hash()).or_default();
+                match moves.iter_mut().find(|(played, _)| *played == mv) {
+                    Some((_, count)) => *count += 1,
+                    None => moves.push((mv, 1)),
+                }
+                board.make_move(mv);
+            }
+        }
+        Ok(Book { positions })
+    }
+
+    /// Number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Most played move of the position, if the book has a legal one.
+    pub fn probe(&self, board: &Board) -> Option<Move> {
+        let legal = board.legal_moves();
+        self.positions
+            .get(&board.hash())?
+            .iter()
+            .filter(|(mv, _)| legal.contains(*mv))
+            .max_by_key(|(_, count)| *count)
+            .map(|(mv, _)| *mv)
+    }
+}
@@ -0,0 +1,301 @@
+// epd.rs
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+use crate::board::{Board, FenParseError};
+use crate::negamax::{format_score, negamax, SearchOptions, SearchSignals, Verbosity};
+use crate::piece_move::Move;
+use crate::san::SanParseError;
+use crate::time_manager::SearchLimits;
+use crate::transposition::TranspositionTable;
+
+/// Reasons an EPD line can't be read.
+#[derive(Debug)]
+pub enum EpdError {
+    /// Malformed operation or missing position fields.
+    Syntax { line: usize, message: String },
+    /// The position fields don't make a valid position.
+    Position { line: usize, error: FenParseError },
+    /// A `bm` or `am` operand that is not a legal move.
+    Move { line: usize, opcode: String, reason: SanParseError },
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            EpdError::Position { line, error } => write!(f, "line {line}: {error}"),
+            EpdError::Move { line, opcode, reason } => write!(f, "line {line}: {opcode} {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// A position of a test suite with its operations.
+#[derive(Clone)]
+pub struct Epd {
+    pub board: Board,
+    /// Moves to find, from `bm`.
+    pub best_moves: Vec<Move>,
+    /// Moves to avoid, from `am`.
+    pub avoid_moves: Vec<Move>,
+    /// Position name, from `id`.
+    pub id: Option<String>,
+    /// Comment, from `c0`.
+    pub comment: Option<String>,
+    /// Perft counts by depth, from `D1`, `D2` and so on.
+    pub perft: Vec<(u8, u128)>,
+    /// Every other operation, opcode then operands.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+
+    /// Whether `mv` is one of the best moves and none of the moves to avoid.
+    pub fn is_solved_by(&self, mv: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&mv)) &&
+        !self.avoid_moves.contains(&mv)
+    }
+
+    /// Whether there is a `bm` or `am` to check a search against.
+    pub fn has_solution(&self) -> bool {
+        !self.best_moves.is_empty() || !self.avoid_moves.is_empty()
+    }
+}
+
+impl FromStr for Epd {
+    type Err = EpdError;
+
+    /// Read a single EPD line.
+    fn from_str(line: &str) -> Result<Epd, EpdError> {
+        parse_line(line, 1)
+    }
+}
+
+/// Read every position of an EPD file. Blank lines and lines starting with
+/// `#` are skipped.
+pub fn parse_epd(text: &str) -> Result<Vec<Epd>, EpdError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| parse_line(line, i + 1))
+        .collect()
+}
+
+/// First word of `text` and the text after it. Words end at whitespace or
+/// at the `;` closing an operation.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == ';')
+        .unwrap_or(text.len());
+    (&text[..end], &text[end..])
+}
+
+/// Operands of an operation, quoted strings are a single operand.
+fn split_operands(text: &str) -> Option<Vec<String>> {
+    let mut operands = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut operand = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => operand.push(c),
+                    None => return None,
+                }
+            }
+            operands.push(operand);
+        } else {
+            let mut operand = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                operand.push(c);
+                chars.next();
+            }
+            operands.push(operand);
+        }
+    }
+    Some(operands)
+}
+
+/// Read the four position fields, the optional move counters and the
+/// operations of an EPD line. Operations end with `;`, the perft files also
+/// start them with one.
+fn parse_line(text: &str, line: usize) -> Result<Epd, EpdError> {
+    let syntax = |message: String| EpdError::Syntax { line, message };
+
+    let mut rest = text.trim();
+    let mut fields = Vec::new();
+    for _ in 0..4 {
+        let (field, tail) = split_word(rest);
+        if field.is_empty() {
+            return Err(syntax(String::from("missing position fields")));
+        }
+        fields.push(field);
+        rest = tail;
+    }
+
+    // FEN style counters instead of hmvc and fmvn
+    let mut halfmove = None;
+    let mut fullmove = None;
+    let (first, tail) = split_word(rest);
+    let (second, tail) = split_word(tail);
+    if let (Ok(h), Ok(f)) = (first.parse::<u16>(), second.parse::<u16>()) {
+        halfmove = Some(h);
+        fullmove = Some(f);
+        rest = tail;
+    }
+
+    // split on the semicolons outside of quotes
+    let mut operations = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                operations.push(&rest[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    operations.push(&rest[start..]);
+
+    let mut parsed = Vec::new();
+    for operation in operations.into_iter().map(str::trim).filter(|op| !op.is_empty()) {
+        let (opcode, operands) = split_word(operation);
+        let operands = split_operands(operands)
+            .ok_or_else(|| syntax(format!("unterminated string in '{operation}'")))?;
+        parsed.push((opcode.to_string(), operands));
+    }
+
+    let counter = |opcode: &str| {
+        parsed
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .and_then(|(_, operands)| operands.first())
+            .and_then(|n| n.parse::<u16>().ok())
+    };
+    let halfmove = halfmove.or_else(|| counter("hmvc")).unwrap_or(0);
+    let fullmove = fullmove.or_else(|| counter("fmvn")).unwrap_or(1);
+
+    let fen = format!("{} {halfmove} {fullmove}", fields.join(" "));
+    let board = Board::new(&fen).map_err(|error| EpdError::Position { line, error })?;
+
+    let mut epd = Epd {
+        board,
+        best_moves: Vec::new(),
+        avoid_moves: Vec::new(),
+        id: None,
+        comment: None,
+        perft: Vec::new(),
+        operations: Vec::new(),
+    };
+
+    for (opcode, operands) in parsed {
+        match opcode.as_str() {
+            "bm" | "am" => {
+                let mut moves = Vec::new();
+                for san in &operands {
+                    let mv = epd.board.parse_san(san).map_err(|reason| EpdError::Move {
+                        line,
+                        opcode: opcode.clone(),
+                        reason,
+                    })?;
+                    moves.push(mv);
+                }
+                if opcode == "bm" {
+                    epd.best_moves = moves;
+                } else {
+                    epd.avoid_moves = moves;
+                }
+            },
+            "id" => epd.id = operands.into_iter().next(),
+            "c0" => epd.comment = operands.into_iter().next(),
+            "hmvc" | "fmvn" => {},
+            _ if opcode.starts_with('D') && opcode[1..].parse::<u8>().is_ok_and(|d| d > 0) => {
+                let depth = opcode[1..].parse().unwrap_or(0);
+                let nodes = operands
+                    .first()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| syntax(format!("invalid perft count for {opcode}")))?;
+                epd.perft.push((depth, nodes));
+            },
+            _ => epd.operations.push((opcode, operands)),
+        }
+    }
+
+    Ok(epd)
+}
+
+/// Search every position of a test suite with a fresh transposition table
+/// and print whether the move played is one of its best moves and none of
+/// the moves to avoid. Positions without `bm` or `am` are skipped. Returns
+/// the number of positions solved and failed.
+pub fn bench_epd(
+    suite: &[Epd],
+    tt: &mut TranspositionTable,
+    limits: &SearchLimits,
+    options: &SearchOptions,
+) -> (usize, usize) {
+    let signals = SearchSignals::default();
+    let start = Instant::now();
+    let mut solved = 0;
+    let mut failed = 0;
+    let mut nodes = 0;
+
+    for (i, epd) in suite.iter().enumerate() {
+        let id = epd.id.clone().unwrap_or_else(|| format!("position {}", i + 1));
+        if !epd.has_solution() {
+            println!("{}/{} {id}: skipped, no bm or am", i + 1, suite.len());
+            continue;
+        }
+
+        tt.clear();
+        let mut board = epd.board.clone();
+        let result = negamax(&mut board, tt, limits, options, &signals, Verbosity::Quiet);
+        nodes += result.nodes;
+
+        let verdict = if epd.is_solved_by(result.best_move) {
+            solved += 1;
+            "solved"
+        } else {
+            failed += 1;
+            "failed"
+        };
+
+        let san = |moves: &[Move]| {
+            moves.iter().map(|&mv| epd.board.move_to_san(mv)).collect::<Vec<_>>().join(" ")
+        };
+        let mut expected = String::new();
+        if !epd.best_moves.is_empty() {
+            expected += &format!(" bm {}", san(&epd.best_moves));
+        }
+        if !epd.avoid_moves.is_empty() {
+            expected += &format!(" am {}", san(&epd.avoid_moves));
+        }
+
+        println!(
+            "{}/{} {id}: {verdict}, played {} (expected{expected}) score {} depth {}",
+            i + 1,
+            suite.len(),
+            epd.board.move_to_san(result.best_move),
+            format_score(result.score),
+            result.depth,
+        );
+    }
+
+    println!("\nSolved: {solved}/{}", solved + failed);
+    println!("Failed: {failed}");
+    println!("Nodes:  {nodes}");
+    println!("Time:   {} ms", start.elapsed().as_millis());
+
+    (solved, failed)
+}
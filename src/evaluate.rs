@@ -85,6 +85,13 @@ const MIRRORED: [usize; 64] = [
 ];
 
 
+/// Material value of the piece on bitboard `index`, regardless of color.
+#[inline]
+pub fn piece_value(index: usize) -> i32 {
+    PIECE_VALUES[index].abs()
+}
+
+#[allow(clippy::clone_on_copy, clippy::needless_range_loop, clippy::needless_return, clippy::while_let_loop)]
 pub fn eval(board: &Board, color: Color) -> i32 {
     let mut score = 0;
 
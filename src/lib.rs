@@ -0,0 +1,41 @@
+// lib.rs
+
+//! Linkus chess engine.
+//!
+//! Bitboard based move generation, negamax search and a UCI front end. The
+//! attack tables are filled lazily the first time a `Board` is created, but
+//! `gen_tables` can be called up front to pay that cost at startup.
+
+pub mod board;
+pub mod book;
+pub mod negamax;
+pub mod color;
+pub mod epd;
+pub mod piece_move;
+pub mod piece;
+pub mod bitboard;
+pub mod evaluate;
+pub mod move_ordering;
+pub mod move_tables;
+pub mod options;
+pub mod sliding_attacks;
+pub mod square;
+pub mod parse;
+pub mod perft;
+pub mod pgn;
+pub mod san;
+pub mod time_manager;
+pub mod transposition;
+pub mod zobrist;
+
+pub use crate::bitboard::Bitboard;
+pub use crate::board::Board;
+pub use crate::color::Color;
+pub use crate::evaluate::eval;
+pub use crate::move_tables::gen_tables;
+pub use crate::negamax::{negamax, SearchOptions, SearchResult};
+pub use crate::parse::uci_loop;
+pub use crate::perft::{perft, perft_driver};
+pub use crate::piece::Piece;
+pub use crate::piece_move::{Move, MoveList};
+pub use crate::square::Square;
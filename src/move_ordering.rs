@@ -0,0 +1,297 @@
+// move_ordering.rs
+
+use crate::board::Board;
+use crate::color::Color;
+use crate::evaluate::piece_value;
+use crate::negamax::MAX_PLY;
+use crate::piece_move::*;
+
+/// History scores are halved once one of them reaches this.
+const HISTORY_MAX: i32 = 1 << 20;
+
+/// Puts capturing evasions ahead of every quiet one.
+const EVASION_CAPTURE_SCORE: i32 = 1 << 28;
+
+/// Quiet moves that caused beta cutoffs, remembered to try them early in
+/// other nodes of the same search.
+pub struct MoveOrdering {
+    /// Two most recent cutoff moves for every ply.
+    killers: [[Move; 2]; MAX_PLY as usize + 1],
+    /// Butterfly table indexed by color, origin and target.
+    history: [[[i32; 64]; 64]; 2],
+}
+
+impl MoveOrdering {
+
+    pub fn new() -> MoveOrdering {
+        MoveOrdering {
+            killers: [[NO_MOVE; 2]; MAX_PLY as usize + 1],
+            history: [[[0; 64]; 64]; 2],
+        }
+    }
+
+    /// Killer moves stored for `ply`.
+    #[inline]
+    pub fn killers(&self, ply: u8) -> [Move; 2] {
+        self.killers[ply as usize]
+    }
+
+    /// History score of a quiet move for `color`.
+    #[inline]
+    pub fn history(&self, color: Color, mv: Move) -> i32 {
+        self.history[color.to_index()]
+            [mv.get_origin().to_index()]
+            [mv.get_target().to_index()]
+    }
+
+    /// Record a quiet move of the side to move that caused a beta cutoff.
+    pub fn update(&mut self, board: &Board, mv: Move, depth: u8, ply: u8) {
+        let killers = &mut self.killers[ply as usize];
+        if killers[0] != mv {
+            killers[1] = killers[0];
+            killers[0] = mv;
+        }
+
+        let color = board.get_to_move().to_index();
+        let entry = &mut self.history[color]
+            [mv.get_origin().to_index()]
+            [mv.get_target().to_index()];
+        *entry += depth as i32 * depth as i32;
+
+        // keep recent cutoffs weighing more than old ones
+        if *entry >= HISTORY_MAX {
+            for score in self.history.iter_mut().flatten().flatten() {
+                *score /= 2;
+            }
+        }
+    }
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        MoveOrdering::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    HashMove,
+    GenerateCaptures,
+    Captures,
+    GenerateQuiets,
+    Killers,
+    Quiets,
+    BadCaptures,
+    GenerateEvasions,
+    Evasions,
+    Done,
+}
+
+/// Hands out the pseudo legal moves of a position best first, generating
+/// and sorting each group of moves only when it is reached: the hash move,
+/// captures and promotions that do not lose material by MVV-LVA, killer
+/// moves, the remaining quiet moves by history score, then losing captures.
+pub struct MovePicker {
+    stage: Stage,
+    hash_move: Move,
+    killers: [Move; 2],
+    /// Moves of the current stage with their scores.
+    moves: Vec<(Move, i32)>,
+    /// Moves before this index were already returned.
+    index: usize,
+    /// Captures put off because static exchange says they lose material.
+    bad_captures: Vec<Move>,
+    /// Go on to quiet moves after the captures.
+    quiets: bool,
+}
+
+impl MovePicker {
+
+    /// Picker for the main search.
+    pub fn new(hash_move: Move, killers: [Move; 2]) -> MovePicker {
+        MovePicker {
+            stage: Stage::HashMove,
+            hash_move,
+            killers,
+            moves: Vec::new(),
+            index: 0,
+            bad_captures: Vec::new(),
+            quiets: true,
+        }
+    }
+
+    /// Picker for the quiescence search, captures and promotions only.
+    pub fn captures() -> MovePicker {
+        MovePicker {
+            stage: Stage::GenerateCaptures,
+            hash_move: NO_MOVE,
+            killers: [NO_MOVE; 2],
+            moves: Vec::new(),
+            index: 0,
+            bad_captures: Vec::new(),
+            quiets: false,
+        }
+    }
+
+    /// Picker for a side to move in check, every legal evasion with the hash
+    /// move first, then captures by MVV-LVA and quiet moves by history.
+    pub fn evasions(hash_move: Move) -> MovePicker {
+        MovePicker {
+            stage: Stage::GenerateEvasions,
+            hash_move,
+            killers: [NO_MOVE; 2],
+            moves: Vec::new(),
+            index: 0,
+            bad_captures: Vec::new(),
+            quiets: true,
+        }
+    }
+
+    /// Next move to search, `None` once every move was returned.
+    pub fn next(&mut self, board: &Board, ordering: &MoveOrdering) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if self.hash_move != NO_MOVE && board.is_pseudo_legal(self.hash_move) {
+                        return Some(self.hash_move);
+                    }
+                },
+                Stage::GenerateCaptures => {
+                    let hash_move = self.hash_move;
+                    self.moves = board
+                        .pseudo_legal_captures()
+                        .into_vec()
+                        .into_iter()
+                        .filter(|&mv| mv != hash_move)
+                        .map(|mv| (mv, mvv_lva(board, mv)))
+                        .collect();
+                    self.index = 0;
+                    self.stage = Stage::Captures;
+                },
+                Stage::Captures => {
+                    if let Some(mv) = self.pick_best() {
+                        // in the main search losing captures wait until
+                        // after the quiet moves
+                        if self.quiets && !mv.is_promotion() && !board.see_ge(mv, 0) {
+                            self.bad_captures.push(mv);
+                            continue;
+                        }
+                        return Some(mv);
+                    }
+                    self.stage = if self.quiets { Stage::GenerateQuiets } else { Stage::Done };
+                },
+                Stage::GenerateQuiets => {
+                    let hash_move = self.hash_move;
+                    let color = board.get_to_move();
+                    self.moves = board
+                        .pseudo_legal_moves()
+                        .into_vec()
+                        .into_iter()
+                        .filter(|&mv| !mv.is_capture() && !mv.is_promotion() && mv != hash_move)
+                        .map(|mv| (mv, ordering.history(color, mv)))
+                        .collect();
+                    self.index = 0;
+                    self.stage = Stage::Killers;
+                },
+                Stage::Killers => {
+                    // killers come from sibling nodes, only play them if
+                    // they were generated here
+                    for i in 0..2 {
+                        let killer = self.killers[i];
+                        self.killers[i] = NO_MOVE;
+                        if killer == NO_MOVE {
+                            continue;
+                        }
+                        if let Some(at) = self.moves.iter().position(|&(mv, _)| mv == killer) {
+                            self.moves.swap_remove(at);
+                            return Some(killer);
+                        }
+                    }
+                    self.stage = Stage::Quiets;
+                },
+                Stage::Quiets => {
+                    if let Some(mv) = self.pick_best() {
+                        return Some(mv);
+                    }
+                    self.bad_captures.reverse();
+                    self.stage = Stage::BadCaptures;
+                },
+                Stage::BadCaptures => {
+                    match self.bad_captures.pop() {
+                        Some(mv) => return Some(mv),
+                        None => self.stage = Stage::Done,
+                    }
+                },
+                Stage::GenerateEvasions => {
+                    let hash_move = self.hash_move;
+                    let color = board.get_to_move();
+                    self.moves = board
+                        .evasions()
+                        .into_vec()
+                        .into_iter()
+                        .map(|mv| {
+                            let score = if mv == hash_move {
+                                i32::MAX
+                            } else if mv.is_capture() || mv.is_promotion() {
+                                EVASION_CAPTURE_SCORE + mvv_lva(board, mv)
+                            } else {
+                                ordering.history(color, mv)
+                            };
+                            (mv, score)
+                        })
+                        .collect();
+                    self.index = 0;
+                    self.stage = Stage::Evasions;
+                },
+                Stage::Evasions => {
+                    if let Some(mv) = self.pick_best() {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::Done;
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Move the best scored remaining move to the front and return it.
+    fn pick_best(&mut self) -> Option<Move> {
+        if self.index >= self.moves.len() {
+            return None;
+        }
+
+        let mut best = self.index;
+        for i in self.index + 1..self.moves.len() {
+            if self.moves[i].1 > self.moves[best].1 {
+                best = i;
+            }
+        }
+        self.moves.swap(self.index, best);
+        self.index += 1;
+
+        Some(self.moves[self.index - 1].0)
+    }
+}
+
+/// Most valuable victim, least valuable attacker. Promotions count the
+/// promoted piece as part of the victim.
+fn mvv_lva(board: &Board, mv: Move) -> i32 {
+    let victim = match mv.get_special() {
+        SpecialMove::EnPassant => piece_value(5),
+        _ => match board.get_bitboard_index_at_square(mv.get_target()) {
+            Some(index) => piece_value(index),
+            None => 0,
+        },
+    };
+    let promotion = match mv.get_special() {
+        SpecialMove::Promotion(p) | SpecialMove::PromotionCapture(p) => piece_value(p.to_index()),
+        _ => 0,
+    };
+    let attacker = match board.get_bitboard_index_at_square(mv.get_origin()) {
+        Some(index) => piece_value(index),
+        None => 0,
+    };
+
+    (victim + promotion) * 1000 - attacker
+}
@@ -1,6 +1,10 @@
 // move_tables.rs
 
+use std::sync::Once;
 use crate::bitboard::*;
+use crate::square::{Square, ALL_SQUARES};
+use crate::sliding_attacks::gen_sliding_attacks;
+use crate::zobrist::gen_keys;
 
 pub static mut PAWN_PUSHES: [[Bitboard; 64]; 2] = [[EMPTY; 64]; 2];
 pub static mut PAWN_DOUBLE_PUSHES: [[Bitboard; 64]; 2] = [[EMPTY; 64]; 2];
@@ -9,6 +13,11 @@ pub static mut PAWN_ATTACKS: [[Bitboard; 64]; 2] = [[EMPTY; 64]; 2];
 pub static mut KNIGHT_ATTACKS: [Bitboard; 64] = [EMPTY; 64];
 pub static mut KING_ATTACKS: [Bitboard; 64]= [EMPTY; 64];
 
+/// Squares strictly between two squares on a shared rank, file or diagonal.
+pub static mut BETWEEN: [[Bitboard; 64]; 64] = [[EMPTY; 64]; 64];
+/// Whole rank, file or diagonal through two aligned squares.
+pub static mut LINE: [[Bitboard; 64]; 64] = [[EMPTY; 64]; 64];
+
 
 pub const NOT_A_FILE: u64 = 18374403900871474942;
 pub const NOT_H_FILE: u64 = 9187201950435737471; 
@@ -16,6 +25,7 @@ pub const NOT_AB_FILE: u64 = 18229723555195321596;
 pub const NOT_GH_FILE: u64 = 4557430888798830399;
 
 
+#[allow(clippy::needless_range_loop)]
 fn gen_pawn_pushes() {
     for i in 0..64 {
         let loc = 1u64 << i;
@@ -26,6 +36,7 @@ fn gen_pawn_pushes() {
     }
 }
 
+#[allow(clippy::needless_range_loop)]
 fn gen_pawn_double_pushes() {
     for i in 0..64 {
         let loc = 1u64 << i;
@@ -42,6 +53,7 @@ fn gen_pawn_double_pushes() {
     }
 }
 
+#[allow(clippy::needless_range_loop)]
 fn gen_pawn_attacks() {
     for i in 0..64 {
         let loc = 1u64 << i;
@@ -54,6 +66,7 @@ fn gen_pawn_attacks() {
     }
 }
 
+#[allow(clippy::needless_range_loop)]
 fn gen_knight_attacks() {
     for i in 0..64 {
         let loc = 1u64 << i;
@@ -70,6 +83,7 @@ fn gen_knight_attacks() {
     }
 }
 
+#[allow(clippy::needless_range_loop)]
 fn gen_king_attacks() {
     for i in 0..64 {
         let loc = 1u64 << i;
@@ -86,10 +100,60 @@ fn gen_king_attacks() {
     }
 }
 
+fn gen_between_and_lines() {
+    let directions: [fn(&Square) -> Option<Square>; 8] = [
+        Square::due_n,
+        Square::due_s,
+        Square::due_e,
+        Square::due_w,
+        Square::due_ne,
+        Square::due_sw,
+        Square::due_nw,
+        Square::due_se,
+    ];
+
+    for origin in ALL_SQUARES {
+        for (d, step) in directions.iter().enumerate() {
+            // opposite direction sits next to it in the list
+            let back = directions[d ^ 1];
+
+            // full line through origin along this direction
+            let mut line = Bitboard::from_square(origin);
+            for walk in [step, &back] {
+                let mut sq = origin;
+                while let Some(next) = walk(&sq) {
+                    line |= Bitboard::from_square(next);
+                    sq = next;
+                }
+            }
+
+            let mut between = EMPTY;
+            let mut sq = origin;
+            while let Some(next) = step(&sq) {
+                unsafe {
+                    BETWEEN[origin.to_index()][next.to_index()] = between;
+                    LINE[origin.to_index()][next.to_index()] = line;
+                }
+                between |= Bitboard::from_square(next);
+                sq = next;
+            }
+        }
+    }
+}
+
+static TABLES_INIT: Once = Once::new();
+
+/// Fill all precomputed attack tables. Safe to call more than once, only the
+/// first call does any work.
 pub fn gen_tables() {
-    gen_pawn_pushes();
-    gen_pawn_double_pushes();
-    gen_pawn_attacks();
-    gen_knight_attacks();
-    gen_king_attacks();
+    TABLES_INIT.call_once(|| {
+        gen_pawn_pushes();
+        gen_pawn_double_pushes();
+        gen_pawn_attacks();
+        gen_knight_attacks();
+        gen_king_attacks();
+        gen_sliding_attacks();
+        gen_between_and_lines();
+        gen_keys();
+    });
 }
\ No newline at end of file
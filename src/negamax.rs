@@ -1,97 +1,939 @@
 // negamax.rs
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use crate::board::Board;
 use crate::color::Color;
-use crate::evaluate::eval;
+use crate::evaluate::{eval, piece_value};
+use crate::move_ordering::{MoveOrdering, MovePicker};
 use crate::piece_move::*;
+use crate::time_manager::{SearchLimits, TimeManager, DEFAULT_MOVE_OVERHEAD};
+use crate::transposition::{Bound, TranspositionTable};
 
-pub fn negamax_driver(board: &mut Board, alpha: i32, beta: i32, depth: u8) -> i32 {
+/// Deepest iteration the search will start.
+pub const MAX_DEPTH: u8 = 64;
 
+/// Deepest ply the quiescence search will go.
+pub const MAX_PLY: u8 = 128;
 
-    if depth == 0 { return eval(board, board.get_to_move()) }
+/// Bound of the search window, larger than any score.
+pub const INFINITY: i32 = 10000000;
+
+/// Score of giving mate on the board. Mate in `ply` plies scores
+/// `MATE - ply`, so faster mates score higher.
+pub const MATE: i32 = 1000000;
+
+/// Scores beyond this are mate scores.
+const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+/// How many nodes to search between looking at the clock.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Half width of the first aspiration window around the last score.
+const ASPIRATION_WINDOW: i32 = 25;
+
+/// Iterations before this one search with the full window.
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+
+/// Shallowest depth null move pruning is tried at.
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+
+/// Shallowest depth late move reductions are done at.
+const LMR_MIN_DEPTH: u8 = 3;
+
+/// Moves searched at full depth before late move reductions start.
+const LMR_MIN_MOVES: u8 = 3;
+
+/// Shallowest depth the hash move is tested for singularity at.
+const SINGULAR_MIN_DEPTH: u8 = 8;
+
+/// Margin per ply of depth other moves must stay below the hash move score
+/// for it to be singular.
+const SINGULAR_MARGIN: i32 = 2;
+
+/// Slack for delta pruning, a capture that cannot bring the score within
+/// this of alpha is not searched.
+const DELTA_MARGIN: i32 = 200;
+
+/// What the search prints while it runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verbosity {
+    /// Print nothing.
+    Quiet,
+    /// Print a UCI `info` line after every iteration.
+    Uci,
+    /// Like `Uci`, plus the score of every root move and a summary.
+    Debug,
+}
+
+/// Flags another thread can use to steer a running search.
+#[derive(Clone, Default)]
+pub struct SearchSignals {
+    /// Abort the search as soon as possible.
+    pub stop: Arc<AtomicBool>,
+    /// Ignore the clock, the engine is thinking on the opponent's time.
+    pub pondering: Arc<AtomicBool>,
+}
+
+impl SearchSignals {
+
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+}
+
+/// Most lines the search reports at once.
+pub const MAX_MULTI_PV: usize = 64;
+
+/// Search settings set through UCI options. The feature switches are mostly
+/// there to measure features against each other in engine matches.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchOptions {
+    /// Principal variation search, null windows after the first move.
+    pub pvs: bool,
+    /// Null move pruning.
+    pub null_move: bool,
+    /// Late move reductions.
+    pub lmr: bool,
+    /// Search a hash move that is much better than the rest a ply deeper.
+    pub singular: bool,
+    /// Search recaptures on the square of the last capture a ply deeper.
+    pub recapture: bool,
+    /// Number of best lines to report.
+    pub multi_pv: usize,
+    /// Time kept back from every move for GUI and communication lag, in ms.
+    pub move_overhead: u64,
+    /// Centipawns a draw is worth less than an even position to the side
+    /// searching, negative to prefer draws.
+    pub contempt: i32,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            pvs: true,
+            null_move: true,
+            lmr: true,
+            singular: false,
+            recapture: false,
+            multi_pv: 1,
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            contempt: 0,
+        }
+    }
+}
+
+/// Outcome of a search.
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: Move,
+    /// Score of `best_move` for the side to move.
+    pub score: i32,
+    /// Expected line of play, starting with `best_move`.
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+    /// Last fully searched depth.
+    pub depth: u8,
+    /// First move and score of every line of the last full iteration, best
+    /// first. More than one with `multi_pv`.
+    pub lines: Vec<(Move, i32)>,
+}
+
+/// Triangular table of principal variations. Row `ply` holds the best line
+/// found so far from the node at that ply.
+pub struct PvTable {
+    lines: Vec<[Move; MAX_PLY as usize + 1]>,
+    lengths: [usize; MAX_PLY as usize + 1],
+}
+
+impl PvTable {
+
+    pub fn new() -> PvTable {
+        PvTable {
+            lines: vec![[NO_MOVE; MAX_PLY as usize + 1]; MAX_PLY as usize + 1],
+            lengths: [0; MAX_PLY as usize + 1],
+        }
+    }
+
+    /// Forget the line at `ply`, done when a node is entered.
+    #[inline]
+    pub fn clear(&mut self, ply: u8) {
+        self.lengths[ply as usize] = 0;
+    }
+
+    /// Make `mv` followed by the line from the next ply the line at `ply`.
+    pub fn update(&mut self, ply: u8, mv: Move) {
+        let ply = ply as usize;
+        let child = if ply < MAX_PLY as usize { self.lengths[ply + 1] } else { 0 };
+
+        self.lines[ply][0] = mv;
+        for i in 0..child.min(MAX_PLY as usize - ply) {
+            self.lines[ply][i + 1] = self.lines[ply + 1][i];
+        }
+        self.lengths[ply] = 1 + child.min(MAX_PLY as usize - ply);
+    }
+
+    /// Best line found from `ply`.
+    pub fn line(&self, ply: u8) -> &[Move] {
+        &self.lines[ply as usize][..self.lengths[ply as usize]]
+    }
+}
+
+impl Default for PvTable {
+    fn default() -> Self {
+        PvTable::new()
+    }
+}
+
+/// State shared by every node of one search.
+pub struct SearchInfo<'a> {
+    pub tt: &'a mut TranspositionTable,
+    pub time: TimeManager,
+    pub signals: SearchSignals,
+    pub node_limit: Option<u64>,
+    pub nodes: u64,
+    /// Deepest ply reached in the current iteration.
+    pub seldepth: u8,
+    pub pv: PvTable,
+    pub ordering: MoveOrdering,
+    pub options: SearchOptions,
+    /// Move played at each ply of the current line, `NO_MOVE` for a null move.
+    pub moves: [Move; MAX_PLY as usize + 1],
+    /// Move left out at each ply while testing a hash move for singularity.
+    pub excluded: [Move; MAX_PLY as usize + 1],
+    /// Root moves of the lines already found in this iteration when
+    /// searching more than one line.
+    pub root_excluded: Vec<Move>,
+    /// Whether the search is still on the opponent's time, the clock starts
+    /// when this turns false.
+    pub pondering: bool,
+    pub stopped: bool,
+}
+
+impl<'a> SearchInfo<'a> {
+
+    pub fn new(
+        tt: &'a mut TranspositionTable,
+        limits: &SearchLimits,
+        options: &SearchOptions,
+        signals: &SearchSignals,
+        color: Color,
+    ) -> SearchInfo<'a> {
+        SearchInfo {
+            tt,
+            time: TimeManager::new(limits, color, options.move_overhead),
+            signals: signals.clone(),
+            node_limit: limits.nodes,
+            nodes: 0,
+            seldepth: 0,
+            pv: PvTable::new(),
+            ordering: MoveOrdering::new(),
+            options: *options,
+            moves: [NO_MOVE; MAX_PLY as usize + 1],
+            excluded: [NO_MOVE; MAX_PLY as usize + 1],
+            root_excluded: Vec::new(),
+            pondering: signals.is_pondering(),
+            stopped: false,
+        }
+    }
+
+    /// Score of a draw `ply` plies from the root, for the side to move there.
+    #[inline]
+    fn draw_score(&self, ply: u8) -> i32 {
+        if ply.is_multiple_of(2) { -self.options.contempt } else { self.options.contempt }
+    }
+
+    /// Whether the clock says to stop starting new iterations.
+    fn out_of_time(&mut self) -> bool {
+        !self.check_ponderhit() && self.time.soft_exceeded()
+    }
+
+    /// Whether the engine is still pondering. On `ponderhit` the clock is
+    /// started over, the budget counts from there and not from `go ponder`.
+    fn check_ponderhit(&mut self) -> bool {
+        if self.pondering && !self.signals.is_pondering() {
+            self.pondering = false;
+            self.time.restart();
+        }
+        self.pondering
+    }
+
+    /// Count a node and check whether the search has run out of time or nodes.
+    #[inline]
+    fn visit(&mut self) {
+        self.nodes += 1;
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            return;
+        }
+        if self.signals.is_stopped() {
+            self.stopped = true;
+        }
+        if !self.check_ponderhit() && self.time.hard_exceeded() {
+            self.stopped = true;
+        }
+        if let Some(limit) = self.node_limit {
+            if self.nodes >= limit {
+                self.stopped = true;
+            }
+        }
+    }
+}
+
+#[allow(clippy::needless_return)]
+pub fn negamax_driver(
+    board: &mut Board,
+    info: &mut SearchInfo,
+    alpha: i32,
+    beta: i32,
+    depth: u8,
+    ply: u8,
+) -> i32 {
+
+    info.pv.clear(ply);
+
+    // extensions can't go past the end of the search stacks
+    if ply >= MAX_PLY { return eval(board, board.get_to_move()) }
+
+    // drawn positions, repeating a position once inside the search is
+    // enough as the side that can avoid it would have
+    if ply > 0 && (
+        board.repetitions() > 0 ||
+        board.is_fifty_move_draw() ||
+        board.is_insufficient_material()
+    ) {
+        return info.draw_score(ply);
+    }
+
+    // check extension, a position in check is never left to the quiescence
+    // search
+    let in_check = board.is_in_check();
+    let depth = if in_check { depth + 1 } else { depth };
+
+    if depth == 0 { return quiescence(board, info, alpha, beta, ply) }
+
+    info.visit();
+    if info.stopped { return 0 }
+
+    info.seldepth = info.seldepth.max(ply);
 
     let mut alpha = alpha;
+    let alpha_orig = alpha;
+
+    // set while checking whether the hash move is singular
+    let excluded = info.excluded[ply as usize];
+
+    // probe the transposition table for a cutoff or a move to try first
+    let mut hash_move = NO_MOVE;
+    let tt_entry = info.tt.probe(board.hash());
+    if let Some(entry) = tt_entry {
+        hash_move = entry.best_move;
+        if entry.depth >= depth && excluded == NO_MOVE {
+            let score = score_from_tt(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower => if score >= beta { return score },
+                Bound::Upper => if score <= alpha { return score },
+            }
+        }
+    }
+
+    let pv_node = beta - alpha > 1;
+
+    // null move pruning: if passing still fails high the position is good
+    // enough to cut, except in pawn endgames where passing can be the best
+    // move (zugzwang) and never twice in a row
+    if info.options.null_move &&
+       !pv_node &&
+       !in_check &&
+       excluded == NO_MOVE &&
+       depth >= NULL_MOVE_MIN_DEPTH &&
+       ply > 0 && info.moves[ply as usize - 1] != NO_MOVE &&
+       board.has_non_pawn_material(board.get_to_move()) &&
+       eval(board, board.get_to_move()) >= beta {
+
+        let reduction = 2 + depth / 6;
+        info.moves[ply as usize] = NO_MOVE;
+        let undo = board.make_null_move();
+        let score = -negamax_driver(
+            board, info, -beta, -beta + 1, depth.saturating_sub(1 + reduction), ply + 1);
+        board.unmake_null_move(undo);
+
+        if info.stopped { return 0 }
+
+        // an unproven mate from a null move search is not trusted
+        if score >= beta {
+            return if score >= MATE_BOUND { beta } else { score };
+        }
+    }
+
+    // singular extension: if every other move falls well short of the
+    // stored score of the hash move, the hash move is searched deeper
+    let mut singular = false;
+    if let Some(entry) = tt_entry {
+        let tt_score = score_from_tt(entry.score, ply);
+        if info.options.singular &&
+           excluded == NO_MOVE &&
+           ply > 0 &&
+           depth >= SINGULAR_MIN_DEPTH &&
+           hash_move != NO_MOVE &&
+           entry.bound != Bound::Upper &&
+           entry.depth + 3 >= depth &&
+           tt_score.abs() < MATE_BOUND {
+
+            let singular_beta = tt_score - SINGULAR_MARGIN * depth as i32;
+            info.excluded[ply as usize] = hash_move;
+            let score = negamax_driver(
+                board, info, singular_beta - 1, singular_beta, (depth - 1) / 2, ply);
+            info.excluded[ply as usize] = NO_MOVE;
+
+            if info.stopped { return 0 }
+
+            singular = score < singular_beta;
+        }
+    }
+
     let mut best_score = i32::MIN;
+    let mut best_move = NO_MOVE;
     let mut legal_moves = 0u8;
-    board.gen_moves();
 
-    let copy = board.clone();
-    for mv in board.get_move_list().into_vec() {
+    let mut picker = if in_check {
+        MovePicker::evasions(hash_move)
+    } else {
+        MovePicker::new(hash_move, info.ordering.killers(ply))
+    };
+    while let Some(mv) = picker.next(board, &info.ordering) {
+
+        if mv == excluded {
+            continue;
+        }
+
+        // recapture extension, taking back on the square of the last capture
+        let recapture = info.options.recapture && ply > 0 && mv.is_capture() && {
+            let last = info.moves[ply as usize - 1];
+            last != NO_MOVE && last.is_capture() && last.get_target() == mv.get_target()
+        };
+
+        info.moves[ply as usize] = mv;
+        if let Some(undo) = board.make_move(mv) {
+
+            let extension = if (singular && mv == hash_move) || recapture { 1 } else { 0 };
+            let new_depth = depth - 1 + extension;
+
+            // late quiet moves are searched shallower first, as they are
+            // unlikely to be best after the ordering
+            let quiet = !mv.is_capture() && !mv.is_promotion();
+            let reduction = if info.options.lmr &&
+                quiet &&
+                extension == 0 &&
+                !in_check &&
+                !board.is_in_check() &&
+                depth >= LMR_MIN_DEPTH &&
+                legal_moves >= LMR_MIN_MOVES {
+                if legal_moves >= 2 * LMR_MIN_MOVES && depth >= 2 * LMR_MIN_DEPTH { 2 } else { 1 }
+            } else {
+                0
+            };
+
+            let score = if legal_moves == 0 {
+                -negamax_driver(board, info, -beta, -alpha, new_depth, ply + 1)
+            } else {
+                // prove the move is no better than alpha with a null window
+                // and look again when it is
+                let window = if info.options.pvs { alpha + 1 } else { beta };
+                let mut score = -negamax_driver(
+                    board, info, -window, -alpha, new_depth - reduction, ply + 1);
+                if reduction > 0 && score > alpha {
+                    score = -negamax_driver(board, info, -window, -alpha, new_depth, ply + 1);
+                }
+                if info.options.pvs && score > alpha && score < beta {
+                    score = -negamax_driver(board, info, -beta, -alpha, new_depth, ply + 1);
+                }
+                score
+            };
+            // take back move
+            board.unmake_move(mv, undo);
+
+            if info.stopped { return 0 }
 
-        if board.make_move(mv) {
-            let score = -negamax_driver(board,  -beta, -alpha, depth - 1);
             if score > best_score {
                 best_score = score;
+                best_move = mv;
+            }
+            if score > alpha {
+                alpha = score;
+                info.pv.update(ply, mv);
             }
             legal_moves += 1;
         }
-        // take back move
-        *board = copy.clone();
 
-        if best_score > alpha {
-            alpha = best_score;
-        }
         if alpha >= beta {
+            if !mv.is_capture() && !mv.is_promotion() {
+                info.ordering.update(board, mv, depth, ply);
+            }
             break;
         }
     }
 
-    // check for mate
     if legal_moves == 0 {
-        if board.is_in_check() {
-            match board.get_to_move() {
-                Color::WHITE => {
-                    return 10000000
-                },
-                Color::BLACK => {
-                    return -10000000
-                }
-            }
+        // the hash move was the only move, so it is singular
+        if excluded != NO_MOVE {
+            return alpha;
+        }
+        // check for mate
+        if in_check {
+            return -MATE + ply as i32;
         } else {
-            return 0;
+            return info.draw_score(ply);
         }
     }
 
+    if excluded == NO_MOVE {
+        let bound = if best_score <= alpha_orig {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        info.tt.store(board.hash(), depth, score_to_tt(best_score, ply), bound, best_move);
+    }
+
     return best_score;
 
 }
 
-pub fn negamax(board: &mut Board, depth: u8, print: bool) -> Move {
+/// Mate scores are stored in the transposition table as distance to mate
+/// from the stored position rather than from the root.
+fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Turn a stored score back into a score relative to the root.
+fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Search captures, en passant and promotions until the position is quiet,
+/// so the static evaluation is never taken in the middle of an exchange.
+pub fn quiescence(
+    board: &mut Board,
+    info: &mut SearchInfo,
+    alpha: i32,
+    beta: i32,
+    ply: u8,
+) -> i32 {
+
+    info.visit();
+    if info.stopped { return 0 }
+
+    info.seldepth = info.seldepth.max(ply);
+
+    if ply >= MAX_PLY { return eval(board, board.get_to_move()) }
+
+    // stand pat, the side to move does not have to capture, unless it is in
+    // check and has to find an evasion
+    let in_check = board.is_in_check();
+    let stand_pat = if in_check {
+        -MATE + ply as i32
+    } else {
+        eval(board, board.get_to_move())
+    };
+    if stand_pat >= beta {
+        return stand_pat;
+    }
+
+    let mut alpha = alpha.max(stand_pat);
+    let mut best_score = stand_pat;
+
+    let mut picker = if in_check {
+        MovePicker::evasions(NO_MOVE)
+    } else {
+        MovePicker::captures()
+    };
+    while let Some(mv) = picker.next(board, &info.ordering) {
+
+        if !in_check && !mv.is_promotion() {
+            // delta pruning, even winning the piece would not raise alpha
+            if stand_pat + capture_gain(board, mv) + DELTA_MARGIN <= alpha {
+                continue;
+            }
+            // losing captures are not going to help either
+            if !board.see_ge(mv, 0) {
+                continue;
+            }
+        }
+
+        if let Some(undo) = board.make_move(mv) {
+            let score = -quiescence(board, info, -beta, -alpha, ply + 1);
+            board.unmake_move(mv, undo);
+
+            if info.stopped { return 0 }
+
+            if score > best_score {
+                best_score = score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+    }
+
+    best_score
+}
+
+/// Material won by `mv`, counting the captured piece and the promotion.
+fn capture_gain(board: &Board, mv: Move) -> i32 {
+    let mut gain = match mv.get_special() {
+        SpecialMove::EnPassant => piece_value(5),
+        _ => match board.get_bitboard_index_at_square(mv.get_target()) {
+            Some(index) => piece_value(index),
+            None => 0,
+        },
+    };
+    match mv.get_special() {
+        SpecialMove::Promotion(p) | SpecialMove::PromotionCapture(p) => {
+            gain += piece_value(p.to_index()) - piece_value(5);
+        },
+        _ => {},
+    }
+    gain
+}
+
+/// Search the root position to a fixed depth inside the window `alpha` to
+/// `beta`, trying `first` before the other moves. Returns the best move and
+/// its score, the move is only trustworthy if the search was not stopped and
+/// the score is above `alpha`. Moves in `info.root_excluded` are skipped.
+pub fn negamax_root(
+    board: &mut Board,
+    info: &mut SearchInfo,
+    depth: u8,
+    alpha: i32,
+    beta: i32,
+    first: Move,
+    verbosity: Verbosity,
+) -> (Move, i32) {
 
     let mut best_move = NO_MOVE;
     let mut best_score = i32::MIN;
-    board.gen_moves();
+    info.pv.clear(0);
+
+    let mut alpha = alpha;
+    let alpha_orig = alpha;
+
+    let mut searched = 0;
+    let mut picker = MovePicker::new(first, [NO_MOVE; 2]);
+    while let Some(mv) = picker.next(board, &info.ordering) {
+        if info.root_excluded.contains(&mv) {
+            continue;
+        }
+        info.moves[0] = mv;
+        if let Some(undo) = board.make_move(mv) {
+            let mut score;
+            if searched == 0 || !info.options.pvs {
+                score = -negamax_driver(board, info, -beta, -alpha, depth-1, 1);
+            } else {
+                score = -negamax_driver(board, info, -alpha - 1, -alpha, depth-1, 1);
+                if score > alpha && score < beta {
+                    score = -negamax_driver(board, info, -beta, -alpha, depth-1, 1);
+                }
+            }
+            board.unmake_move(mv, undo);
+            searched += 1;
 
-    let mut alpha = -10000000;
-    let beta = 10000000;
+            if info.stopped { break }
 
-    let copy = board.clone();
-    for mv in board.get_move_list().into_vec() {
-        if board.make_move(mv) {
-            let score = -negamax_driver(board, -beta, -alpha, depth-1);
             if score > best_score {
                 best_score = score;
                 best_move = mv;
             }
-            if print {
+            if score > alpha {
+                alpha = score;
+                info.pv.update(0, mv);
+            }
+            if verbosity == Verbosity::Debug {
                 println!("{mv}: {score}");
             }
         }
-        *board = copy.clone();
 
-        if best_score > alpha {
-            alpha = best_score;
-        }
         if alpha >= beta {
             best_move = mv;
             break;
         }
     }
 
-    if print {
-        println!("\nBest move: {best_move}\nScore: {best_score}");
+    // lines after the first are not the best the position has
+    if best_move != NO_MOVE && !info.stopped && info.root_excluded.is_empty() {
+        let bound = if best_score <= alpha_orig {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        info.tt.store(board.hash(), depth, best_score, bound, best_move);
+    }
+
+    (best_move, best_score)
+}
+
+/// Print the UCI `info` line for a finished iteration, or for a search that
+/// fell outside its aspiration window when `bound` is not exact. `line`
+/// numbers the lines from 1 when more than one is searched.
+fn print_info(info: &SearchInfo, depth: u8, line: usize, score: i32, bound: Bound, pv: &[Move]) {
+    let time = info.time.elapsed().as_millis() as u64;
+    let nps = info.nodes * 1000 / time.max(1);
+    let bound = match bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+    };
+    let multi_pv = if info.options.multi_pv > 1 { format!(" multipv {line}") } else { String::new() };
+    // a line that failed low has no moves to show
+    let pv = if pv.is_empty() { String::new() } else { format!(" pv {}", format_pv(pv)) };
+    println!(
+        "info depth {} seldepth {}{} score {}{} nodes {} nps {} time {}{}",
+        depth,
+        info.seldepth,
+        multi_pv,
+        format_score(score),
+        bound,
+        info.nodes,
+        nps,
+        time,
+        pv,
+    );
+}
+
+/// Line found at the root, which must start with `best_move`.
+fn root_pv(info: &SearchInfo, best_move: Move) -> Vec<Move> {
+    let pv = info.pv.line(0);
+    if pv.first() == Some(&best_move) {
+        pv.to_vec()
+    } else {
+        vec![best_move]
+    }
+}
+
+/// UCI form of a score, `cp <centipawns>` or `mate <moves>` where a negative
+/// number of moves means the engine is getting mated.
+pub fn format_score(score: i32) -> String {
+    if score >= MATE_BOUND {
+        format!("mate {}", (MATE - score + 1) / 2)
+    } else if score <= -MATE_BOUND {
+        format!("mate {}", -(MATE + score) / 2)
+    } else {
+        format!("cp {score}")
+    }
+}
+
+/// Space separated moves of a principal variation.
+pub fn format_pv(pv: &[Move]) -> String {
+    pv.iter()
+        .map(|mv| mv.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Search the position with iterative deepening until the depth, node or
+/// time limits are reached, or `signals.stop` is raised, and return the best
+/// move found with its line. Each iteration starts with the best move of the
+/// previous one. With `options.multi_pv` above one every iteration also
+/// searches the next best lines, leaving out the first moves of the lines
+/// before them, and reports each of them. `limits.searchmoves` leaves out
+/// the other root moves and `limits.mate` stops once a short enough mate is
+/// found.
+#[allow(clippy::needless_return)]
+pub fn negamax(
+    board: &mut Board,
+    tt: &mut TranspositionTable,
+    limits: &SearchLimits,
+    options: &SearchOptions,
+    signals: &SearchSignals,
+    verbosity: Verbosity,
+) -> SearchResult {
+
+    let mut info = SearchInfo::new(tt, limits, options, signals, board.get_to_move());
+    let mate_depth = limits.mate.map(|moves| moves.saturating_mul(2).saturating_sub(1));
+    let max_depth = limits.depth
+        .unwrap_or(MAX_DEPTH)
+        .min(mate_depth.unwrap_or(MAX_DEPTH))
+        .clamp(1, MAX_DEPTH);
+
+    // mated or stalemated, there is nothing to search
+    let legal = board.legal_moves().into_vec();
+    if legal.is_empty() {
+        let score = if board.is_in_check() { -MATE } else { info.draw_score(0) };
+        return SearchResult { best_move: NO_MOVE, score, pv: Vec::new(), nodes: 0, depth: 0, lines: Vec::new() };
+    }
+
+    // root moves left out by searchmoves, unless none of them is legal
+    let searched = |mv: &Move| limits.searchmoves.iter().any(|uci| *uci == mv.to_string());
+    let excluded: Vec<Move> = if legal.iter().any(searched) {
+        legal.iter().copied().filter(|mv| !searched(mv)).collect()
+    } else {
+        Vec::new()
+    };
+    let lines = options.multi_pv.clamp(1, legal.len() - excluded.len());
+
+    // start from the stored move of an earlier search of this position
+    let mut best_move = match info.tt.probe(board.hash()) {
+        Some(entry) if !excluded.contains(&entry.best_move) => entry.best_move,
+        _ => NO_MOVE,
+    };
+    let mut best_score: i32 = 0;
+    let mut pv = Vec::new();
+    let mut completed = 0;
+    // first move and score of every line of the last iteration
+    let mut previous: Vec<(Move, i32)> = Vec::new();
+    'deepening: for depth in 1..=max_depth {
+        info.seldepth = 0;
+        info.root_excluded.clone_from(&excluded);
+        let mut found: Vec<(Move, i32)> = Vec::new();
+
+        for line in 0..lines {
+            let (mut line_move, line_score) = match line {
+                0 => (best_move, best_score),
+                _ => previous.get(line).copied().unwrap_or((NO_MOVE, 0)),
+            };
+
+            // search a narrow window around the last score, widening it on
+            // the side the score falls out of
+            let mut delta = ASPIRATION_WINDOW;
+            let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH &&
+                                           line < previous.len() &&
+                                           line_score.abs() < MATE_BOUND {
+                (
+                    line_score.saturating_sub(delta).max(-INFINITY),
+                    line_score.saturating_add(delta).min(INFINITY),
+                )
+            } else {
+                (-INFINITY, INFINITY)
+            };
+
+            let score = loop {
+                let (mv, score) = negamax_root(board, &mut info, depth, alpha, beta, line_move, verbosity);
+
+                if info.stopped {
+                    // a partial first iteration is still better than nothing
+                    if completed == 0 && line == 0 && mv != NO_MOVE {
+                        best_move = mv;
+                    }
+                    break 'deepening;
+                }
+
+                if score <= alpha {
+                    if verbosity != Verbosity::Quiet {
+                        let pv = if line == 0 { pv.clone() } else { Vec::new() };
+                        print_info(&info, depth, line + 1, score, Bound::Upper, &pv);
+                    }
+                    alpha = score.saturating_sub(delta).max(-INFINITY);
+                } else if score >= beta {
+                    // the move that failed high is worth trying first
+                    line_move = mv;
+                    if verbosity != Verbosity::Quiet {
+                        print_info(&info, depth, line + 1, score, Bound::Lower, &root_pv(&info, mv));
+                    }
+                    beta = score.saturating_add(delta).min(INFINITY);
+                } else {
+                    line_move = mv;
+                    break score;
+                }
+                delta *= 2;
+            };
+
+            if line == 0 {
+                best_move = line_move;
+                best_score = score;
+                pv = root_pv(&info, best_move);
+            }
+            if verbosity != Verbosity::Quiet {
+                print_info(&info, depth, line + 1, score, Bound::Exact, &root_pv(&info, line_move));
+            }
+            found.push((line_move, score));
+            info.root_excluded.push(line_move);
+        }
+
+        completed = depth;
+        previous = found;
+
+        // a mate as short as "go mate" asked for
+        if mate_depth.is_some_and(|plies| best_score >= MATE - plies as i32) {
+            break;
+        }
+        if info.out_of_time() {
+            break;
+        }
+    }
+
+    // out of time before anything was searched
+    if best_move == NO_MOVE || !legal.contains(&best_move) || excluded.contains(&best_move) {
+        best_move = legal.iter().copied().find(|mv| !excluded.contains(mv)).unwrap_or(NO_MOVE);
+    }
+    if pv.first() != Some(&best_move) {
+        pv = if best_move == NO_MOVE { Vec::new() } else { vec![best_move] };
     }
 
+    if verbosity == Verbosity::Debug {
+        println!(
+            "\nBest move: {best_move}\nScore: {}\nPV: {}",
+            format_score(best_score),
+            format_pv(&pv),
+        );
+    }
 
-    return best_move;
+    return SearchResult {
+        best_move,
+        score: best_score,
+        pv,
+        nodes: info.nodes,
+        depth: completed,
+        lines: previous,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mate_scores_count_moves() {
+        assert_eq!(format_score(MATE - 1), "mate 1");
+        assert_eq!(format_score(MATE - 3), "mate 2");
+        assert_eq!(format_score(MATE - 9), "mate 5");
+        assert_eq!(format_score(MATE_BOUND), format!("mate {}", (MAX_PLY as i32 + 1) / 2));
+
+        assert_eq!(format_score(-MATE), "mate 0");
+        assert_eq!(format_score(-(MATE - 2)), "mate -1");
+        assert_eq!(format_score(-(MATE - 4)), "mate -2");
+        assert_eq!(format_score(-MATE_BOUND), format!("mate -{}", MAX_PLY / 2));
+    }
+
+    #[test]
+    fn other_scores_are_centipawns() {
+        assert_eq!(format_score(0), "cp 0");
+        assert_eq!(format_score(-35), "cp -35");
+        assert_eq!(format_score(MATE_BOUND - 1), format!("cp {}", MATE_BOUND - 1));
+        assert_eq!(format_score(-(MATE_BOUND - 1)), format!("cp -{}", MATE_BOUND - 1));
+    }
 }
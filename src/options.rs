@@ -0,0 +1,249 @@
+// options.rs
+
+use std::fmt;
+use crate::negamax::{SearchOptions, MAX_MULTI_PV};
+use crate::time_manager::MAX_MOVE_OVERHEAD;
+use crate::transposition::{DEFAULT_HASH_MB, MAX_HASH_MB, MIN_HASH_MB};
+
+/// Largest contempt accepted, in centipawns either way.
+const MAX_CONTEMPT: i64 = 100;
+
+/// Reasons a `setoption` is turned down.
+#[derive(Debug, PartialEq)]
+pub enum OptionError {
+    /// No option with that name.
+    Unknown(String),
+    /// No value for an option that needs one.
+    MissingValue(String),
+    /// A value the option doesn't accept, the option then the value.
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionError::Unknown(name)          => write!(f, "unknown option '{name}'"),
+            OptionError::MissingValue(name)     => write!(f, "missing argument for {name}"),
+            OptionError::InvalidValue(name, v)  => write!(f, "invalid {name} '{v}'"),
+        }
+    }
+}
+
+impl std::error::Error for OptionError {}
+
+/// Every option the engine has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionId {
+    Hash,
+    ClearHash,
+    Threads,
+    MultiPv,
+    MoveOverhead,
+    Contempt,
+    BookFile,
+    Ponder,
+    Pvs,
+    NullMove,
+    Lmr,
+    SingularExtension,
+    RecaptureExtension,
+}
+
+/// How an option is set and what it accepts.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionKind {
+    Check { default: bool },
+    Spin { default: i64, min: i64, max: i64 },
+    String { default: String },
+    /// Does something when set, no value.
+    Button,
+}
+
+/// A value for an option, checked against its kind.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionValue {
+    Check(bool),
+    Spin(i64),
+    String(String),
+    Button,
+}
+
+impl OptionValue {
+
+    /// Number of a spin value. `parse_value` only gives values of the
+    /// option's own kind, other kinds count as 0.
+    pub fn as_spin(&self) -> i64 {
+        match self {
+            OptionValue::Spin(n) => *n,
+            _ => 0,
+        }
+    }
+
+    /// Whether a check value is on, other kinds count as off.
+    pub fn as_check(&self) -> bool {
+        matches!(self, OptionValue::Check(true))
+    }
+
+    /// Text of a string value, other kinds count as empty.
+    pub fn as_str(&self) -> &str {
+        match self {
+            OptionValue::String(text) => text,
+            _ => "",
+        }
+    }
+}
+
+/// An option the engine advertises in answer to `uci`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineOption {
+    pub id: OptionId,
+    pub name: &'static str,
+    pub kind: OptionKind,
+}
+
+impl EngineOption {
+
+    fn check(id: OptionId, name: &'static str, default: bool) -> EngineOption {
+        EngineOption { id, name, kind: OptionKind::Check { default } }
+    }
+
+    fn spin(id: OptionId, name: &'static str, default: i64, min: i64, max: i64) -> EngineOption {
+        EngineOption { id, name, kind: OptionKind::Spin { default, min, max } }
+    }
+
+    /// The `option name ... type ...` line.
+    pub fn declaration(&self) -> String {
+        let name = self.name;
+        match &self.kind {
+            OptionKind::Check { default } => {
+                format!("option name {name} type check default {default}")
+            },
+            OptionKind::Spin { default, min, max } => {
+                format!("option name {name} type spin default {default} min {min} max {max}")
+            },
+            OptionKind::String { default } => {
+                let default = if default.is_empty() { "<empty>" } else { default };
+                format!("option name {name} type string default {default}")
+            },
+            OptionKind::Button => format!("option name {name} type button"),
+        }
+    }
+
+    /// Check the value of a `setoption` command against the kind of option.
+    pub fn parse_value(&self, value: Option<&str>) -> Result<OptionValue, OptionError> {
+        let invalid = |value: &str| OptionError::InvalidValue(self.name.to_string(), value.to_string());
+        let missing = || OptionError::MissingValue(self.name.to_string());
+
+        match &self.kind {
+            OptionKind::Check { .. } => match value.ok_or_else(missing)? {
+                "true" => Ok(OptionValue::Check(true)),
+                "false" => Ok(OptionValue::Check(false)),
+                other => Err(invalid(other)),
+            },
+            OptionKind::Spin { min, max, .. } => {
+                let text = value.ok_or_else(missing)?;
+                match text.parse::<i64>() {
+                    Ok(n) if (*min..=*max).contains(&n) => Ok(OptionValue::Spin(n)),
+                    _ => Err(invalid(text)),
+                }
+            },
+            // an empty string is sent as "<empty>", or with no value at all
+            OptionKind::String { .. } => match value {
+                None | Some("<empty>") => Ok(OptionValue::String(String::new())),
+                Some(text) => Ok(OptionValue::String(text.to_string())),
+            },
+            OptionKind::Button => Ok(OptionValue::Button),
+        }
+    }
+}
+
+/// Every option the engine has, in the order they are advertised.
+pub fn engine_options() -> Vec<EngineOption> {
+    let defaults = SearchOptions::default();
+    vec![
+        EngineOption::spin(
+            OptionId::Hash, "Hash", DEFAULT_HASH_MB as i64, MIN_HASH_MB as i64, MAX_HASH_MB as i64,
+        ),
+        EngineOption { id: OptionId::ClearHash, name: "Clear Hash", kind: OptionKind::Button },
+        // the search runs on a single thread
+        EngineOption::spin(OptionId::Threads, "Threads", 1, 1, 1),
+        EngineOption::spin(OptionId::MultiPv, "MultiPV", defaults.multi_pv as i64, 1, MAX_MULTI_PV as i64),
+        EngineOption::spin(
+            OptionId::MoveOverhead, "Move Overhead", defaults.move_overhead as i64, 0, MAX_MOVE_OVERHEAD as i64,
+        ),
+        EngineOption::spin(OptionId::Contempt, "Contempt", defaults.contempt as i64, -MAX_CONTEMPT, MAX_CONTEMPT),
+        EngineOption {
+            id: OptionId::BookFile,
+            name: "BookFile",
+            kind: OptionKind::String { default: String::new() },
+        },
+        // tells the GUI it may send "go ponder"
+        EngineOption::check(OptionId::Ponder, "Ponder", false),
+        EngineOption::check(OptionId::Pvs, "PVS", defaults.pvs),
+        EngineOption::check(OptionId::NullMove, "NullMove", defaults.null_move),
+        EngineOption::check(OptionId::Lmr, "LMR", defaults.lmr),
+        EngineOption::check(OptionId::SingularExtension, "SingularExtension", defaults.singular),
+        EngineOption::check(OptionId::RecaptureExtension, "RecaptureExtension", defaults.recapture),
+    ]
+}
+
+/// Option called `name`, ignoring case, with the value given for it.
+pub fn parse_option(name: &str, value: Option<&str>) -> Result<(EngineOption, OptionValue), OptionError> {
+    let option = engine_options()
+        .into_iter()
+        .find(|option| option.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| OptionError::Unknown(name.to_string()))?;
+    let value = option.parse_value(value)?;
+    Ok((option, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declarations() {
+        let lines: Vec<String> = engine_options().iter().map(|o| o.declaration()).collect();
+        assert!(lines.contains(&String::from("option name Hash type spin default 16 min 1 max 1024")));
+        assert!(lines.contains(&String::from("option name Clear Hash type button")));
+        assert!(lines.contains(&String::from("option name MultiPV type spin default 1 min 1 max 64")));
+        assert!(lines.contains(&String::from("option name BookFile type string default <empty>")));
+        assert!(lines.contains(&String::from("option name PVS type check default true")));
+        assert!(lines.contains(&String::from("option name RecaptureExtension type check default false")));
+    }
+
+    #[test]
+    fn values_are_checked() {
+        assert_eq!(parse_option("hash", Some("64")).map(|(o, v)| (o.id, v)), Ok((OptionId::Hash, OptionValue::Spin(64))));
+        assert_eq!(parse_option("Move Overhead", Some("0")).map(|(_, v)| v), Ok(OptionValue::Spin(0)));
+        assert_eq!(parse_option("LMR", Some("false")).map(|(_, v)| v), Ok(OptionValue::Check(false)));
+        assert_eq!(parse_option("Clear Hash", None).map(|(_, v)| v), Ok(OptionValue::Button));
+        assert_eq!(
+            parse_option("BookFile", Some("<empty>")).map(|(_, v)| v),
+            Ok(OptionValue::String(String::new())),
+        );
+        assert_eq!(
+            parse_option("BookFile", Some("books/main line.pgn")).map(|(_, v)| v),
+            Ok(OptionValue::String(String::from("books/main line.pgn"))),
+        );
+
+        assert_eq!(
+            parse_option("Hash", Some("0")),
+            Err(OptionError::InvalidValue(String::from("Hash"), String::from("0"))),
+        );
+        assert_eq!(
+            parse_option("Threads", Some("4")),
+            Err(OptionError::InvalidValue(String::from("Threads"), String::from("4"))),
+        );
+        assert_eq!(
+            parse_option("MultiPV", Some("lots")),
+            Err(OptionError::InvalidValue(String::from("MultiPV"), String::from("lots"))),
+        );
+        assert_eq!(
+            parse_option("PVS", Some("yes")),
+            Err(OptionError::InvalidValue(String::from("PVS"), String::from("yes"))),
+        );
+        assert_eq!(parse_option("Contempt", None), Err(OptionError::MissingValue(String::from("Contempt"))));
+        assert_eq!(parse_option("Skill Level", Some("3")), Err(OptionError::Unknown(String::from("Skill Level"))));
+    }
+}
@@ -1,305 +1,760 @@
 // parse.rs
 
-use crate::board::Board;
-use crate::negamax::negamax;
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::board::{Board, FenParseError, STARTPOS};
+use crate::book::Book;
+use crate::epd::{bench_epd, parse_epd};
+use crate::negamax::{negamax, SearchOptions, SearchSignals, Verbosity, MAX_DEPTH};
+use crate::options::{engine_options, parse_option, OptionError, OptionId};
 use crate::perft::perft;
-use crate::square::Square;
 use crate::piece_move::*;
-use crate::piece::Piece;
-
-pub fn uci_loop() {
-    let mut board = Board::start();
-    loop {
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input).expect("Failed to read input.");
-
-        let chars: Vec<char> = input
-            .clone()
-            .chars()
-            .collect();
-
-        // check for "d" -> display board
-        let command: String = chars.iter().take(1).collect();
-        if &command == "d" {
-            println!("{board}");
-            continue
-        }
-
-        // check for "isready"
-        let command: String = chars.iter().take(7).collect();
-        if &command == "isready" {
-            println!("readyok");
-            continue
-        }
+use crate::time_manager::SearchLimits;
+use crate::transposition::*;
+
+/// Command keywords, anything before the first of them is skipped.
+const COMMANDS: [&str; 14] = [
+    "uci", "debug", "isready", "setoption", "register", "ucinewgame", "position",
+    "go", "stop", "ponderhit", "quit", "d", "bench", "perft",
+];
+
+/// Keywords of a `go` command.
+const GO_KEYWORDS: [&str; 12] = [
+    "searchmoves", "ponder", "wtime", "btime", "winc", "binc", "movestogo",
+    "depth", "nodes", "mate", "movetime", "infinite",
+];
+
+/// Depth of a `go searchmoves` without any other limit.
+const SEARCHMOVES_DEPTH: u8 = 10;
+
+/// A line of input from the GUI.
+#[derive(Debug, PartialEq)]
+pub enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: Option<String> },
+    Register,
+    UciNewGame,
+    /// `fen` is `None` for the starting position. Moves are in UCI notation
+    /// and are checked when the position is set.
+    Position { fen: Option<String>, moves: Vec<String> },
+    Go(SearchLimits),
+    /// `go perft <depth>`
+    Perft(u8),
+    Stop,
+    PonderHit,
+    Quit,
+    /// `d`, print the board.
+    Display,
+    /// `bench epd <file> <limits>`
+    Bench { path: String, limits: SearchLimits },
+}
 
-        // check for "position"
-        let command: String = chars.iter().take(8).collect();
-        if &command == "position" {
-            board = match parse_position(&input) {
-                Some(b) => b,
-                None => { board },
-            };
-            continue
-        }
+/// Reasons a line of input can't be used.
+#[derive(Debug, PartialEq)]
+pub enum UciError {
+    /// Nothing but whitespace.
+    Empty,
+    /// No known command in the line.
+    UnknownCommand(String),
+    /// A command or keyword without the argument it needs.
+    MissingArgument(String),
+    /// An argument that can't be read, the keyword then the argument.
+    InvalidArgument(String, String),
+    /// The FEN of a `position` command is not a valid position.
+    Fen(FenParseError),
+    /// A move of a `position` command that is not legal.
+    IllegalMove(String),
+    /// A `setoption` for an option the engine doesn't have.
+    UnknownOption(String),
+}
 
-        // check for "ucinewgame"
-        let command: String = chars.iter().take(10).collect();
-        if &command == "ucinewgame" {
-            board = Board::start();
-            continue
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciError::Empty                     => write!(f, "empty command"),
+            UciError::UnknownCommand(command)   => write!(f, "unknown command '{command}'"),
+            UciError::MissingArgument(name)     => write!(f, "missing argument for {name}"),
+            UciError::InvalidArgument(name, v)  => write!(f, "invalid {name} '{v}'"),
+            UciError::Fen(error)                => write!(f, "{error}"),
+            UciError::IllegalMove(mv)           => write!(f, "illegal move '{mv}'"),
+            UciError::UnknownOption(name)       => write!(f, "unknown option '{name}'"),
         }
+    }
+}
 
+impl std::error::Error for UciError {}
 
-        // check for "go"
-        let command: String = chars.iter().take(2).collect();
-        if &command == "go" {
-            parse_go(&mut board, &input);
-            continue
-        }
-
-        // check for "quit"
-        let command: String = chars.iter().take(4).collect();
-        if &command == "quit" {
-            break
+impl From<OptionError> for UciError {
+    fn from(error: OptionError) -> UciError {
+        match error {
+            OptionError::Unknown(name)             => UciError::UnknownOption(name),
+            OptionError::MissingValue(name)        => UciError::MissingArgument(name),
+            OptionError::InvalidValue(name, value) => UciError::InvalidArgument(name, value),
         }
-
-        // check for "uci"
-        let command: String = chars.iter().take(3).collect();
-        if &command == "uci" {
-            println!("id name linkus");
-            println!("id author Jalen Archer");
-            println!("uciok");
-            continue
-        }
-
-        println!("Unknown command.");
     }
 }
 
-fn parse_move(board: &mut Board, input: &str) -> Option<Move> {
-    board.gen_moves();
-
-    let mut chars = input.chars();
-
-    let mut first_square = chars
-        .next()
-        .unwrap()
-        .to_string();
-
-    first_square.push(
-        chars.next().unwrap()
-    );
-    let origin = Square::from_alg(&first_square).unwrap();
-
-    let mut second_square = chars
-        .next()
-        .unwrap()
-        .to_string();
-
-    second_square.push(
-        chars.next().unwrap()
-    );
-    let target = Square::from_alg(&second_square).unwrap();
-
-    let promoted_type = match chars.next() {
-        Some(c) => {
-            if c != '\n' {
-                c
-            } else {
-                ' '
-            }
-        },
-        None => {
-            ' '
-        }
-    };
+/// Search running on the worker thread.
+struct SearchThread {
+    handle: JoinHandle<()>,
+    /// Search only ends on "stop" ("go infinite" or "go ponder").
+    open_ended: bool,
+}
 
-    for mv in board.get_move_list().into_vec() {
-        if mv.get_origin() != origin || mv.get_target() != target {
-            continue
+pub fn uci_loop() {
+    let mut board = Board::start();
+    let tt = Arc::new(Mutex::new(TranspositionTable::new(DEFAULT_HASH_MB)));
+    let signals = SearchSignals::default();
+    let mut options = SearchOptions::default();
+    let mut book: Option<Book> = None;
+    let mut debug = false;
+    let mut search: Option<SearchThread> = None;
+    loop {
+        let mut input = String::new();
+        match std::io::stdin().read_line(&mut input) {
+            // stdin closed, let a limited search finish then quit
+            Ok(0) | Err(_) => {
+                match search.take() {
+                    Some(s) if !s.open_ended => join_search(s),
+                    other => {
+                        search = other;
+                        stop_search(&mut search, &signals);
+                    },
+                }
+                break
+            },
+            Ok(_) => {},
         }
 
-        match mv.get_special() {
-            SpecialMove::Promotion(p) => {
-                match p {
-                    Piece::Queen => {
-                        if promoted_type == 'q' {
-                            return Some(mv)
-                        }
-                    },
-                    Piece::Rook => {
-                        if promoted_type == 'r' {
-                            return Some(mv)
-                        }
-                    },
-                    Piece::Bishop => {
-                        if promoted_type == 'b' {
-                            return Some(mv)
-                        }
-                    },
-                    Piece::Knight => {
-                        if promoted_type == 'n' {
-                            return Some(mv)
-                        }
-                    },
-                    _ => continue
+        let command = match parse_command(&input) {
+            Ok(command) => command,
+            Err(UciError::Empty) => continue,
+            Err(e) => {
+                println!("info string {e}");
+                continue
+            },
+        };
+
+        match command {
+            UciCommand::Uci => {
+                println!("id name linkus");
+                println!("id author Jalen Archer");
+                for option in engine_options() {
+                    println!("{}", option.declaration());
                 }
+                println!("uciok");
             },
-            SpecialMove::PromotionCapture(p) => {
-                match p {
-                    Piece::Queen => {
-                        if promoted_type == 'q' {
-                            return Some(mv)
-                        }
-                    },
-                    Piece::Rook => {
-                        if promoted_type == 'r' {
-                            return Some(mv)
-                        }
-                    },
-                    Piece::Bishop => {
-                        if promoted_type == 'b' {
-                            return Some(mv)
-                        }
-                    },
-                    Piece::Knight => {
-                        if promoted_type == 'n' {
-                            return Some(mv)
-                        }
-                    },
-                    _ => continue
+            UciCommand::Debug(on) => debug = on,
+            UciCommand::IsReady => println!("readyok"),
+            UciCommand::SetOption { name, value } => {
+                stop_search(&mut search, &signals);
+                let tt = &mut lock(&tt);
+                if let Err(e) = set_option(tt, &mut options, &mut book, &name, value.as_deref()) {
+                    println!("info string {e}");
                 }
             },
-            _ => {
-                if promoted_type == ' ' { 
-                    return Some(mv) 
-                } else { 
-                    return None 
+            UciCommand::Register => {},
+            UciCommand::UciNewGame => {
+                stop_search(&mut search, &signals);
+                board = Board::start();
+                lock(&tt).clear();
+            },
+            UciCommand::Position { fen, moves } => {
+                // keep the old position if this one is broken
+                match set_position(fen.as_deref(), &moves) {
+                    Ok(b) => board = b,
+                    Err(e) => println!("info string {e}"),
                 }
-            }
+            },
+            UciCommand::Go(limits) => {
+                stop_search(&mut search, &signals);
+
+                // a book move is played right away, unless the GUI waits for
+                // "stop" to ask for the move
+                let book_move = match &book {
+                    Some(book) if !limits.infinite && !limits.ponder => book.probe(&board),
+                    _ => None,
+                };
+                if let Some(mv) = book_move {
+                    println!("info string book move");
+                    println!("bestmove {mv}");
+                    continue
+                }
+
+                let verbosity = if debug { Verbosity::Debug } else { Verbosity::Uci };
+                search = Some(start_search(&board, &tt, &options, &signals, limits, verbosity));
+            },
+            UciCommand::Perft(depth) => {
+                stop_search(&mut search, &signals);
+                perft(&mut board.clone(), depth);
+            },
+            UciCommand::Stop => stop_search(&mut search, &signals),
+            UciCommand::PonderHit => signals.pondering.store(false, Ordering::Relaxed),
+            UciCommand::Quit => {
+                stop_search(&mut search, &signals);
+                break
+            },
+            UciCommand::Display => println!("{board}"),
+            UciCommand::Bench { path, limits } => {
+                stop_search(&mut search, &signals);
+                run_bench(&mut lock(&tt), &options, &path, &limits);
+            },
         }
     }
-    None
 }
 
-fn parse_position(input: &str) -> Option<Board> {
+/// Stop the running search, if any, and wait for it to print its move.
+fn stop_search(search: &mut Option<SearchThread>, signals: &SearchSignals) {
+    if let Some(s) = search.take() {
+        signals.stop.store(true, Ordering::Relaxed);
+        signals.pondering.store(false, Ordering::Relaxed);
+        join_search(s);
+    }
+}
 
-    
-    let mut chars: Vec<char> = input
-        .chars()
-        .collect();
+/// Wait for the search thread to end. A thread that panicked is reported to
+/// the GUI instead of taking the engine down with it.
+fn join_search(search: SearchThread) {
+    if let Err(panic) = search.handle.join() {
+        println!("info string search failed: {}", panic_message(&*panic));
+    }
+}
 
+/// Text a panic was raised with.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("unknown error"))
+}
 
-    // skip "position"
-    chars = chars[9..].to_vec();
+/// Lock the transposition table, also after a search panicked holding it.
+fn lock(tt: &Mutex<TranspositionTable>) -> MutexGuard<'_, TranspositionTable> {
+    tt.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
 
-    let mut board = Board::start();
+/// Read a line of GUI input. Unknown tokens before the command are skipped,
+/// as are unknown tokens among its arguments.
+pub fn parse_command(input: &str) -> Result<UciCommand, UciError> {
+    let tokens: Vec<&str> = input.split_whitespace().collect();
+    let Some(&first) = tokens.first() else {
+        return Err(UciError::Empty);
+    };
+    let Some(at) = tokens.iter().position(|t| COMMANDS.contains(t)) else {
+        return Err(UciError::UnknownCommand(first.to_string()));
+    };
+    let args = &tokens[at + 1..];
+
+    match tokens[at] {
+        "uci"        => Ok(UciCommand::Uci),
+        "debug"      => match args.first() {
+            Some(&"on")  => Ok(UciCommand::Debug(true)),
+            Some(&"off") => Ok(UciCommand::Debug(false)),
+            Some(other)  => Err(UciError::InvalidArgument(String::from("debug"), other.to_string())),
+            None         => Err(UciError::MissingArgument(String::from("debug"))),
+        },
+        "isready"    => Ok(UciCommand::IsReady),
+        "setoption"  => parse_setoption(args),
+        "register"   => Ok(UciCommand::Register),
+        "ucinewgame" => Ok(UciCommand::UciNewGame),
+        "position"   => parse_position(args),
+        "go"         => parse_go(args),
+        "stop"       => Ok(UciCommand::Stop),
+        "ponderhit"  => Ok(UciCommand::PonderHit),
+        "quit"       => Ok(UciCommand::Quit),
+        "d"          => Ok(UciCommand::Display),
+        "bench"      => parse_bench(args),
+        // "perft <depth>" as a shorthand for "go perft <depth>"
+        _            => parse_perft(args),
+    }
+}
 
-    // check if fen
-    let command: String = chars.iter().take(3).collect();
-    if &command == "fen" {
-
-        // skip "fen"
-        chars = chars[4..].to_vec();
-
-        // if fen string given update board position
-        if chars.len() != 0 {
-            board = match Board::new(
-                &chars.iter().collect::<String>()
-            ) {
-                Ok(b) => b,
-                Err(_) => return None,
-            };
-        }
+/// `setoption name <id> [value <x>]`, both may contain spaces.
+fn parse_setoption(args: &[&str]) -> Result<UciCommand, UciError> {
+    let missing = || UciError::MissingArgument(String::from("setoption name"));
+    let name_at = args.iter().position(|&t| t == "name").ok_or_else(missing)? + 1;
+    let value_at = args.iter().position(|&t| t == "value");
+
+    let name = match value_at {
+        Some(v) if v >= name_at => args[name_at..v].join(" "),
+        _ => args[name_at..].join(" "),
+    };
+    if name.is_empty() {
+        return Err(missing());
+    }
+    let value = value_at.map(|v| args[v + 1..].join(" "));
+
+    Ok(UciCommand::SetOption { name, value })
+}
+
+/// `position [startpos | fen <fen>] [moves <move> ...]`. A FEN may leave
+/// out the move counters.
+fn parse_position(args: &[&str]) -> Result<UciCommand, UciError> {
+    let moves_at = args.iter().position(|&t| t == "moves").unwrap_or(args.len());
+    let moves = args[(moves_at + 1).min(args.len())..]
+        .iter()
+        .map(|mv| mv.to_string())
+        .collect();
 
-        // skip fen string
-        let mut space_count = 0;
-        let mut skip_num = 0;
-        for (i, c) in chars.iter().enumerate() {
-            if space_count == 6 {
-                skip_num = i;
-                break;
+    let fen = match args.iter().position(|&t| t == "startpos" || t == "fen") {
+        Some(at) if args[at] == "startpos" => None,
+        Some(at) if at < moves_at => {
+            let mut fields = args[at + 1..moves_at].to_vec();
+            match fields.len() {
+                0 => return Err(UciError::MissingArgument(String::from("position fen"))),
+                4 => fields.extend(["0", "1"]),
+                5 => fields.push("1"),
+                _ => {},
             }
-            if *c == ' ' {
-                space_count += 1;
-            } 
-        } 
-        chars = chars[skip_num..].to_vec();
+            Some(fields.join(" "))
+        },
+        _ => return Err(UciError::MissingArgument(String::from("position"))),
+    };
 
-    } else {
+    Ok(UciCommand::Position { fen, moves })
+}
 
-        // check if startpos
-        let command: String = chars.iter().take(8).collect();
+/// Board for a `position` command, the FEN or the starting position with the
+/// moves played on it.
+fn set_position(fen: Option<&str>, moves: &[String]) -> Result<Board, UciError> {
+    let mut board = Board::new(fen.unwrap_or(STARTPOS)).map_err(UciError::Fen)?;
+    for text in moves {
+        let mv = parse_move(&board, text)?;
+        board.make_move(mv);
+    }
+    Ok(board)
+}
 
-        if &command != "startpos" {
-            return None
-        }
+/// Find the legal move written in UCI notation, like `e2e4` or `e7e8q`.
+fn parse_move(board: &Board, text: &str) -> Result<Move, UciError> {
+    let text = text.to_ascii_lowercase();
+    board
+        .legal_moves()
+        .iter()
+        .copied()
+        .find(|mv| mv.to_string() == text)
+        .ok_or(UciError::IllegalMove(text))
+}
 
-        // skip "startpos"
-        chars = chars[9..].to_vec();
+/// Arguments of a `go` command.
+fn parse_go(args: &[&str]) -> Result<UciCommand, UciError> {
+    if args.first() == Some(&"perft") {
+        return parse_perft(&args[1..]);
     }
+    Ok(UciCommand::Go(parse_limits(args)?))
+}
 
-    // check if moves are provided
-    let new_s = chars.iter().collect::<String>();
-    match new_s.split_whitespace().find(|&x| x == "moves") {
-        Some(_) => {
+/// `perft <depth>`
+fn parse_perft(args: &[&str]) -> Result<UciCommand, UciError> {
+    match args.first() {
+        Some(depth) => match depth.parse::<u8>() {
+            Ok(n) if n > 0 => Ok(UciCommand::Perft(n)),
+            _ => Err(UciError::InvalidArgument(String::from("perft depth"), depth.to_string())),
+        },
+        None => Err(UciError::MissingArgument(String::from("perft"))),
+    }
+}
 
-            // skip "moves"
-            chars = chars[6..].to_vec();
+/// `bench epd <file> <movetime <ms> | depth <n> | nodes <n>>`
+fn parse_bench(args: &[&str]) -> Result<UciCommand, UciError> {
+    match args.first() {
+        Some(&"epd") => {},
+        Some(other) => return Err(UciError::InvalidArgument(String::from("bench"), other.to_string())),
+        None => return Err(UciError::MissingArgument(String::from("bench"))),
+    }
+    let Some(path) = args.get(1) else {
+        return Err(UciError::MissingArgument(String::from("bench epd")));
+    };
 
-            // get list of move strings
-            let moves_str: String = chars.iter().collect::<String>();
-            let move_list: Vec<&str> = moves_str.split_whitespace().collect::<Vec<_>>();
+    let limits = parse_limits(&args[2..])?;
+    if limits.infinite || limits.ponder {
+        return Err(UciError::MissingArgument(String::from("bench epd movetime or depth")));
+    }
+    Ok(UciCommand::Bench { path: path.to_string(), limits })
+}
 
-            for m in move_list {
-                let mv = parse_move(&mut board, m).unwrap();
-                board.make_move(mv);
-            }
+/// Parse the search limits of a `go` command. Unknown tokens are skipped, as
+/// are `searchmoves` and `mate` with their arguments.
+fn parse_limits(tokens: &[&str]) -> Result<SearchLimits, UciError> {
+    let mut limits = SearchLimits::default();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let keyword = tokens[i];
+        i += 1;
+
+        match keyword {
+            "infinite" => limits.infinite = true,
+            "ponder" => limits.ponder = true,
+            "searchmoves" => {
+                while i < tokens.len() && !GO_KEYWORDS.contains(&tokens[i]) {
+                    limits.searchmoves.push(tokens[i].to_string());
+                    i += 1;
+                }
+            },
+            "mate" => {
+                let Some(&text) = tokens.get(i) else {
+                    return Err(UciError::MissingArgument(keyword.to_string()));
+                };
+                i += 1;
+                match text.parse::<u8>() {
+                    Ok(moves) if moves > 0 => limits.mate = Some(moves.min(MAX_DEPTH / 2)),
+                    _ => return Err(UciError::InvalidArgument(keyword.to_string(), text.to_string())),
+                }
+            },
+            "wtime" | "btime" | "winc" | "binc" | "movestogo" | "movetime" | "nodes" | "depth" => {
+                let Some(&text) = tokens.get(i) else {
+                    return Err(UciError::MissingArgument(keyword.to_string()));
+                };
+                i += 1;
+
+                // some GUIs send negative times when the clock runs out
+                let value = match text.parse::<i64>() {
+                    Ok(v) => Some(v.max(0) as u64),
+                    Err(_) => return Err(UciError::InvalidArgument(keyword.to_string(), text.to_string())),
+                };
+
+                match keyword {
+                    "wtime"     => limits.wtime = value,
+                    "btime"     => limits.btime = value,
+                    "winc"      => limits.winc = value,
+                    "binc"      => limits.binc = value,
+                    "movestogo" => limits.movestogo = value,
+                    "movetime"  => limits.movetime = value,
+                    "nodes"     => limits.nodes = value,
+                    _           => {
+                        limits.depth = value.map(|d| d.clamp(1, MAX_DEPTH as u64) as u8)
+                    },
+                }
+            },
+            _ => {},
+        }
+    }
 
-        },
-        None => {}
+    // plain "go" has no limits and searches until "stop", while a go that
+    // only names the root moves still has to end on its own
+    if limits.wtime.is_none() && limits.btime.is_none() &&
+       limits.movetime.is_none() && limits.depth.is_none() &&
+       limits.nodes.is_none() && limits.mate.is_none() {
+        if limits.searchmoves.is_empty() {
+            limits.infinite = true;
+        } else {
+            limits.depth = Some(SEARCHMOVES_DEPTH);
+        }
     }
 
-    Some(board)
+    Ok(limits)
 }
 
-fn parse_go(board: &mut Board, input: &str) {
+/// Apply a `setoption` command.
+fn set_option(
+    tt: &mut TranspositionTable,
+    options: &mut SearchOptions,
+    book: &mut Option<Book>,
+    name: &str,
+    value: Option<&str>,
+) -> Result<(), UciError> {
+    let (option, value) = parse_option(name, value)?;
+
+    match option.id {
+        OptionId::Hash => tt.resize(value.as_spin() as usize),
+        OptionId::ClearHash => tt.clear(),
+        // only has one value
+        OptionId::Threads => {},
+        OptionId::MultiPv => options.multi_pv = value.as_spin() as usize,
+        OptionId::MoveOverhead => options.move_overhead = value.as_spin() as u64,
+        OptionId::Contempt => options.contempt = value.as_spin() as i32,
+        OptionId::BookFile => {
+            let path = value.as_str();
+            *book = None;
+            if !path.is_empty() {
+                let text = std::fs::read_to_string(path).map_err(|e| {
+                    UciError::InvalidArgument(String::from("BookFile"), format!("{path}: {e}"))
+                })?;
+                let loaded = Book::from_pgn(&text).map_err(|e| {
+                    UciError::InvalidArgument(String::from("BookFile"), format!("{path}: {e}"))
+                })?;
+                println!("info string book {path} has {} positions", loaded.len());
+                *book = Some(loaded);
+            }
+        },
+        // for the GUI, pondering only starts with "go ponder"
+        OptionId::Ponder => {},
+        OptionId::Pvs => options.pvs = value.as_check(),
+        OptionId::NullMove => options.null_move = value.as_check(),
+        OptionId::Lmr => options.lmr = value.as_check(),
+        OptionId::SingularExtension => options.singular = value.as_check(),
+        OptionId::RecaptureExtension => options.recapture = value.as_check(),
+    }
+    Ok(())
+}
 
-    let mut chars: Vec<char> = input
-        .chars()
-        .collect();
+/// Start searching on a worker thread so the UCI loop can keep answering.
+fn start_search(
+    board: &Board,
+    tt: &Arc<Mutex<TranspositionTable>>,
+    options: &SearchOptions,
+    signals: &SearchSignals,
+    limits: SearchLimits,
+    verbosity: Verbosity,
+) -> SearchThread {
+    signals.stop.store(false, Ordering::Relaxed);
+    signals.pondering.store(limits.ponder, Ordering::Relaxed);
+
+    let open_ended = limits.infinite || limits.ponder;
+    let root = board.clone();
+    let mut board = board.clone();
+    let tt = Arc::clone(tt);
+    let options = *options;
+    let signals = signals.clone();
+    let handle = thread::spawn(move || {
+        let mut tt = lock(&tt);
+        let searched = panic::catch_unwind(AssertUnwindSafe(|| {
+            negamax(&mut board, &mut tt, &limits, &options, &signals, verbosity)
+        }));
+
+        // the GUI waits for a move even when the search failed, so fall back
+        // on the first legal one
+        let (best_move, ponder) = match searched {
+            Ok(result) => (result.best_move, result.pv.get(1).copied()),
+            Err(panic) => {
+                println!("info string search failed: {}", panic_message(&*panic));
+                (root.legal_moves().peek().unwrap_or(NO_MOVE), None)
+            },
+        };
+
+        // when searching forever or pondering the GUI has to ask for the move
+        while (limits.infinite || signals.is_pondering()) && !signals.is_stopped() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        match ponder {
+            Some(ponder) => println!("bestmove {best_move} ponder {ponder}"),
+            None => println!("bestmove {best_move}"),
+        }
+    });
+    SearchThread { handle, open_ended }
+}
 
-    // skip "go"
-    chars = chars[3..].to_vec();
+/// Run the search on every position of an EPD test suite.
+fn run_bench(tt: &mut TranspositionTable, options: &SearchOptions, path: &str, limits: &SearchLimits) {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            println!("info string could not read {path}: {e}");
+            return;
+        },
+    };
+    match parse_epd(&text) {
+        Ok(suite) => {
+            bench_epd(&suite, tt, limits, options);
+        },
+        Err(e) => println!("info string {path}: {e}"),
+    }
+}
 
-    // "depth" command
-    let command: String = chars.iter().take(5).collect();
-    if &command == "depth" {
-        chars = chars[6..].to_vec();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let depth = match chars.iter().collect::<String>().trim().parse::<u32>() {
-            Ok(n) => n,
-            Err(_) => 0,
-        };
+    fn fen_of(fen: Option<&str>, moves: &[&str]) -> Result<String, UciError> {
+        let moves: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+        set_position(fen, &moves).map(|board| board.to_fen())
+    }
 
-        let mv = negamax(board, depth as u8, false);
-        println!("bestmove {mv}");
-        // println!("bestmove d2d4");
-        return;
+    #[test]
+    fn commands_match_whole_tokens() {
+        assert_eq!(parse_command("uci\n"), Ok(UciCommand::Uci));
+        assert_eq!(parse_command("ucinewgame\n"), Ok(UciCommand::UciNewGame));
+        assert_eq!(parse_command("d\n"), Ok(UciCommand::Display));
+        assert_eq!(parse_command("debug on\n"), Ok(UciCommand::Debug(true)));
+        assert_eq!(parse_command("debug off"), Ok(UciCommand::Debug(false)));
+        assert_eq!(parse_command("isready"), Ok(UciCommand::IsReady));
+        assert_eq!(parse_command("stop"), Ok(UciCommand::Stop));
+        assert_eq!(parse_command("ponderhit"), Ok(UciCommand::PonderHit));
+        assert_eq!(parse_command("quit"), Ok(UciCommand::Quit));
+        assert_eq!(parse_command("register later"), Ok(UciCommand::Register));
+
+        assert_eq!(parse_command("ucix"), Err(UciError::UnknownCommand(String::from("ucix"))));
+        assert_eq!(parse_command("dd"), Err(UciError::UnknownCommand(String::from("dd"))));
+        assert_eq!(parse_command("stopped"), Err(UciError::UnknownCommand(String::from("stopped"))));
+        assert_eq!(
+            parse_command("debug maybe"),
+            Err(UciError::InvalidArgument(String::from("debug"), String::from("maybe"))),
+        );
+        assert_eq!(parse_command("debug"), Err(UciError::MissingArgument(String::from("debug"))));
     }
 
-    // "perft" command
-    let command: String = chars.iter().take(5).collect();
-    if &command == "perft" {
-        chars = chars[6..].to_vec();
+    #[test]
+    fn whitespace_and_unknown_tokens() {
+        assert_eq!(parse_command(""), Err(UciError::Empty));
+        assert_eq!(parse_command(" \t \r\n"), Err(UciError::Empty));
+        assert_eq!(parse_command("  isready  \r\n"), Ok(UciCommand::IsReady));
+        assert_eq!(parse_command("joho debug on"), Ok(UciCommand::Debug(true)));
+        assert_eq!(
+            parse_command("go  wtime 1000\tbtime  2000 foo"),
+            Ok(UciCommand::Go(SearchLimits {
+                wtime: Some(1000),
+                btime: Some(2000),
+                ..SearchLimits::default()
+            })),
+        );
+    }
 
-        let depth = match chars.iter().collect::<String>().trim().parse::<u32>() {
-            Ok(n) => n,
-            Err(_) => return,
-        };
+    #[test]
+    fn position_commands() {
+        assert_eq!(
+            parse_command("position startpos"),
+            Ok(UciCommand::Position { fen: None, moves: vec![] }),
+        );
+        assert_eq!(
+            parse_command("position startpos moves e2e4 e7e5"),
+            Ok(UciCommand::Position {
+                fen: None,
+                moves: vec![String::from("e2e4"), String::from("e7e5")],
+            }),
+        );
+        assert_eq!(
+            parse_command("position fen 4k3/8/8/8/8/8/8/4K3 w - - moves e1d1"),
+            Ok(UciCommand::Position {
+                fen: Some(String::from("4k3/8/8/8/8/8/8/4K3 w - - 0 1")),
+                moves: vec![String::from("e1d1")],
+            }),
+        );
+        assert_eq!(
+            parse_command("position startpos moves"),
+            Ok(UciCommand::Position { fen: None, moves: vec![] }),
+        );
+
+        for input in ["position", "position fen", "position moves e2e4", "position fen moves e2e4"] {
+            assert!(matches!(parse_command(input), Err(UciError::MissingArgument(_))), "{input}");
+        }
+    }
 
-        perft(board, depth as u8);
-        return;
+    #[test]
+    fn positions_are_checked() {
+        assert_eq!(
+            fen_of(None, &["e2e4", "e7e5", "g1f3"]),
+            Ok(String::from("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")),
+        );
+        assert_eq!(
+            fen_of(Some("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"), &["b7b8N"]),
+            Ok(String::from("1N2k3/8/8/8/8/8/8/4K3 b - - 0 1")),
+        );
+
+        // encoded as 6, the old no move placeholder
+        assert_eq!(
+            fen_of(Some("6r1/4k3/1R6/8/8/8/5K2/8 b - - 0 1"), &["g8a8"]),
+            Ok(String::from("r7/4k3/1R6/8/8/8/5K2/8 w - - 1 2")),
+        );
+        assert_eq!(fen_of(None, &["e2e5"]), Err(UciError::IllegalMove(String::from("e2e5"))));
+        assert_eq!(fen_of(None, &["e2"]), Err(UciError::IllegalMove(String::from("e2"))));
+        assert_eq!(fen_of(None, &["zz99"]), Err(UciError::IllegalMove(String::from("zz99"))));
+        assert_eq!(fen_of(None, &["e2e4e"]), Err(UciError::IllegalMove(String::from("e2e4e"))));
+        assert_eq!(
+            fen_of(Some("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"), &["b7b8"]),
+            Err(UciError::IllegalMove(String::from("b7b8"))),
+        );
+        assert_eq!(
+            fen_of(Some("4k3/8/8/8/8/8/8/4K3 x - - 0 1"), &[]),
+            Err(UciError::Fen(FenParseError::SideToMove)),
+        );
+        assert!(matches!(fen_of(Some("8/8 w"), &[]), Err(UciError::Fen(_))));
+
+        // parse fine but can't be played from
+        assert_eq!(
+            fen_of(Some("4k3/8/8/8/8/8/4R3/K7 w - - 0 1"), &[]),
+            Err(UciError::Fen(FenParseError::OpponentInCheck)),
+        );
+        assert_eq!(
+            fen_of(Some("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"), &[]),
+            Err(UciError::Fen(FenParseError::PawnOnBackRank)),
+        );
+        assert_eq!(
+            fen_of(Some("4k3/8/8/8/8/8/8/4K2p b - - 0 1"), &[]),
+            Err(UciError::Fen(FenParseError::PawnOnBackRank)),
+        );
     }
 
+    #[test]
+    fn go_commands() {
+        assert_eq!(
+            parse_command("go"),
+            Ok(UciCommand::Go(SearchLimits { infinite: true, ..SearchLimits::default() })),
+        );
+        assert_eq!(
+            parse_command("go depth 0"),
+            Ok(UciCommand::Go(SearchLimits::depth(1))),
+        );
+        assert_eq!(
+            parse_command("go wtime -20 btime 100 movestogo 5"),
+            Ok(UciCommand::Go(SearchLimits {
+                wtime: Some(0),
+                btime: Some(100),
+                movestogo: Some(5),
+                ..SearchLimits::default()
+            })),
+        );
+        assert_eq!(
+            parse_command("go searchmoves e2e4 d2d4 movetime 500"),
+            Ok(UciCommand::Go(SearchLimits {
+                searchmoves: vec![String::from("e2e4"), String::from("d2d4")],
+                ..SearchLimits::movetime(500)
+            })),
+        );
+        assert_eq!(
+            parse_command("go ponder movetime 500"),
+            Ok(UciCommand::Go(SearchLimits { ponder: true, ..SearchLimits::movetime(500) })),
+        );
+        assert_eq!(parse_command("go perft 3"), Ok(UciCommand::Perft(3)));
+        assert_eq!(parse_command("perft 2"), Ok(UciCommand::Perft(2)));
+
+        assert_eq!(
+            parse_command("go depth"),
+            Err(UciError::MissingArgument(String::from("depth"))),
+        );
+        assert_eq!(
+            parse_command("go movetime soon"),
+            Err(UciError::InvalidArgument(String::from("movetime"), String::from("soon"))),
+        );
+        assert!(matches!(parse_command("go perft"), Err(UciError::MissingArgument(_))));
+        assert!(matches!(parse_command("go perft 0"), Err(UciError::InvalidArgument(..))));
+        assert!(matches!(parse_command("go perft 999"), Err(UciError::InvalidArgument(..))));
+    }
+
+    #[test]
+    fn setoption_and_bench_commands() {
+        assert_eq!(
+            parse_command("setoption name Hash value 32"),
+            Ok(UciCommand::SetOption {
+                name: String::from("Hash"),
+                value: Some(String::from("32")),
+            }),
+        );
+        assert_eq!(
+            parse_command("setoption name Clear Hash"),
+            Ok(UciCommand::SetOption { name: String::from("Clear Hash"), value: None }),
+        );
+        assert!(matches!(parse_command("setoption"), Err(UciError::MissingArgument(_))));
+        assert!(matches!(parse_command("setoption name value 3"), Err(UciError::MissingArgument(_))));
+
+        assert_eq!(
+            parse_command("bench epd wac.epd depth 6"),
+            Ok(UciCommand::Bench {
+                path: String::from("wac.epd"),
+                limits: SearchLimits::depth(6),
+            }),
+        );
+        assert!(matches!(parse_command("bench"), Err(UciError::MissingArgument(_))));
+        assert!(matches!(parse_command("bench pgn x"), Err(UciError::InvalidArgument(..))));
+        assert!(matches!(parse_command("bench epd"), Err(UciError::MissingArgument(_))));
+        assert!(matches!(parse_command("bench epd wac.epd"), Err(UciError::MissingArgument(_))));
+    }
 }
@@ -7,30 +7,33 @@ pub fn perft_driver(board: &mut Board, depth: u8) -> u128 {
     if depth == 0 {
         return 1;
     }
-    let copy = board.clone();
-    board.gen_moves();
-    for mv in board.get_move_list().into_vec() {
-        if board.make_move(mv) {
+    let moves = board.legal_moves();
+
+    // every legal move is a leaf, no need to make them
+    if depth == 1 {
+        return moves.len() as u128;
+    }
+
+    for &mv in moves.iter() {
+        if let Some(undo) = board.make_move(mv) {
             leafs += perft_driver(board, depth - 1);
+            board.unmake_move(mv, undo);
         }
-        *board = copy.clone();
     }
     leafs
 }
 
 pub fn perft(board: &mut Board, depth: u8) {
     let mut total: u128 = 0;
-    board.gen_moves();
-    let copy = board.clone();
-    for mv in board.get_move_list().into_vec() {
+    for &mv in board.legal_moves().iter() {
         let mut nodes = 0;
-        if board.make_move(mv) {
+        if let Some(undo) = board.make_move(mv) {
             nodes += perft_driver(board, depth - 1);
             total += nodes;
             print!("{mv}");
-            print!(": {}\n", nodes);
+            println!(": {}", nodes);
+            board.unmake_move(mv, undo);
         }
-        *board = copy.clone();
     }
 
     println!("\nNodes:  {}", total);
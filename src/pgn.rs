@@ -0,0 +1,543 @@
+// pgn.rs
+
+use std::fmt;
+use std::str::FromStr;
+use crate::board::{Board, FenParseError};
+use crate::color::Color;
+use crate::piece_move::Move;
+use crate::san::SanParseError;
+
+/// Export lines are wrapped before this many characters.
+const LINE_WIDTH: usize = 80;
+
+/// Reasons PGN text can't be read.
+#[derive(Debug)]
+pub enum PgnError {
+    /// Malformed PGN at a line of the text.
+    Syntax { line: usize, message: String },
+    /// The FEN tag of a game is not a valid position.
+    Fen { line: usize, error: FenParseError },
+    /// A move that can't be played. `ply` counts half moves from the start
+    /// position of the game, variations included.
+    IllegalMove { line: usize, ply: usize, san: String, reason: SanParseError },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            PgnError::Fen { line, error } => write!(f, "line {line}: invalid FEN tag, {error}"),
+            PgnError::IllegalMove { line, ply, reason, .. } => {
+                write!(f, "line {line}, ply {ply}: {reason}")
+            },
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// A move of a game with its annotations.
+#[derive(Clone)]
+pub struct PgnMove {
+    pub mv: Move,
+    /// Numeric annotation glyphs, `!` is 1, `?` is 2 and so on.
+    pub nags: Vec<u8>,
+    /// Comment following the move.
+    pub comment: Option<String>,
+    /// Alternatives to this move, played from the position before it.
+    pub variations: Vec<Line>,
+}
+
+impl PgnMove {
+    pub fn new(mv: Move) -> PgnMove {
+        PgnMove { mv, nags: Vec::new(), comment: None, variations: Vec::new() }
+    }
+}
+
+/// A sequence of moves, the main line of a game or a variation.
+#[derive(Clone, Default)]
+pub struct Line {
+    /// Comment before the first move.
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+}
+
+/// A game read from or written to PGN.
+#[derive(Clone)]
+pub struct Game {
+    /// Tag pairs in the order they are written.
+    pub tags: Vec<(String, String)>,
+    /// Position before the first move, from the FEN tag if there is one.
+    pub start: Board,
+    pub line: Line,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*`. Written as the Result tag too, the
+    /// one in `tags` is ignored by `to_pgn`.
+    pub result: String,
+}
+
+impl Game {
+
+    /// Game from `start` with the seven tag roster left unknown. A FEN tag
+    /// is added when `start` is not the standard starting position.
+    pub fn new(start: Board) -> Game {
+        let mut tags: Vec<(String, String)> = ["Event", "Site", "Date", "Round", "White", "Black"]
+            .iter()
+            .map(|name| (name.to_string(), String::from("?")))
+            .collect();
+        tags.push((String::from("Result"), String::from("*")));
+
+        let fen = start.to_fen();
+        if fen != Board::start().to_fen() {
+            tags.push((String::from("SetUp"), String::from("1")));
+            tags.push((String::from("FEN"), fen));
+        }
+
+        Game { tags, start, line: Line::default(), result: String::from("*") }
+    }
+
+    /// Value of the tag `name`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the tag `name`, adding it after the others if it is missing.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Moves of the main line.
+    pub fn moves(&self) -> Vec<Move> {
+        self.line.moves.iter().map(|m| m.mv).collect()
+    }
+
+    /// Positions of the main line, the start position first and the
+    /// position after every move following it.
+    pub fn boards(&self) -> Vec<Board> {
+        let mut boards = vec![self.start.clone()];
+        let mut board = self.start.clone();
+        for m in &self.line.moves {
+            board.make_move(m.mv);
+            boards.push(board.clone());
+        }
+        boards
+    }
+
+    /// Position at the end of the main line.
+    pub fn board(&self) -> Board {
+        let mut board = self.start.clone();
+        for m in &self.line.moves {
+            board.make_move(m.mv);
+        }
+        board
+    }
+
+    /// Play a move at the end of the main line, if it is legal there.
+    pub fn push(&mut self, mv: Move) -> Result<(), SanParseError> {
+        let board = self.board();
+        if !board.legal_moves().iter().any(|&legal| legal == mv) {
+            return Err(SanParseError::Illegal(mv.to_string()));
+        }
+        self.line.moves.push(PgnMove::new(mv));
+        Ok(())
+    }
+
+    /// Write the game as PGN with moves in SAN.
+    pub fn to_pgn(&self) -> String {
+        let mut tags = self.tags.clone();
+        match tags.iter_mut().find(|(name, _)| name == "Result") {
+            Some((_, value)) => *value = self.result.clone(),
+            None => tags.push((String::from("Result"), self.result.clone())),
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in &tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn += &format!("[{name} \"{value}\"]\n");
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        write_line(&self.start, &self.line, &mut tokens);
+        tokens.push(self.result.clone());
+
+        // no space inside the parentheses of a variation
+        let mut line = String::new();
+        let mut prev = "";
+        for token in &tokens {
+            let glued = prev == "(" || token == ")";
+            if !line.is_empty() && !glued && line.len() + 1 + token.len() > LINE_WIDTH {
+                pgn += &line;
+                pgn.push('\n');
+                line.clear();
+            } else if !line.is_empty() && !glued {
+                line.push(' ');
+            }
+            line += token;
+            prev = token;
+        }
+        pgn += &line;
+        pgn.push('\n');
+        pgn
+    }
+}
+
+/// Movetext tokens of `line` played from `board`.
+fn write_line(board: &Board, line: &Line, tokens: &mut Vec<String>) {
+    let mut board = board.clone();
+    if let Some(comment) = &line.comment {
+        write_comment(comment, tokens);
+    }
+
+    // black moves get a number at the start of a line and after anything
+    // that interrupts the moves
+    let mut number = true;
+    for m in &line.moves {
+        let fullmove = board.get_fullmove_number();
+        match board.get_to_move() {
+            Color::WHITE => tokens.push(format!("{fullmove}.")),
+            Color::BLACK if number => tokens.push(format!("{fullmove}...")),
+            Color::BLACK => {},
+        }
+        debug_assert!(board.legal_moves().iter().any(|&legal| legal == m.mv));
+        tokens.push(board.move_to_san(m.mv));
+        number = false;
+
+        for nag in &m.nags {
+            tokens.push(format!("${nag}"));
+        }
+        if let Some(comment) = &m.comment {
+            write_comment(comment, tokens);
+            number = true;
+        }
+        for variation in &m.variations {
+            tokens.push(String::from("("));
+            write_line(&board, variation, tokens);
+            tokens.push(String::from(")"));
+            number = true;
+        }
+
+        board.make_move(m.mv);
+    }
+}
+
+/// Brace comment split into words, so long comments wrap too.
+fn write_comment(comment: &str, tokens: &mut Vec<String>) {
+    let comment = format!("{{{}}}", comment.replace('}', ""));
+    tokens.extend(comment.split_whitespace().map(String::from));
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    Result(String),
+    /// SAN move or move number.
+    Symbol(String),
+}
+
+/// Split PGN text into tokens with the line they start on.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let start = line;
+        let at_line_start = line_start;
+        line_start = c == '\n';
+
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {},
+            // escaped line
+            '%' if at_line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        line_start = true;
+                        break;
+                    }
+                }
+            },
+            ';' => {
+                let mut comment = String::new();
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        line_start = true;
+                        break;
+                    }
+                    comment.push(c);
+                }
+                tokens.push((Token::Comment(comment.trim().to_string()), start));
+            },
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            comment.push(c);
+                        },
+                        None => return Err(PgnError::Syntax {
+                            line: start,
+                            message: String::from("unterminated comment"),
+                        }),
+                    }
+                }
+                let words: Vec<&str> = comment.split_whitespace().collect();
+                tokens.push((Token::Comment(words.join(" ")), start));
+            },
+            '[' => {
+                let syntax = |message: &str| PgnError::Syntax {
+                    line: start,
+                    message: message.to_string(),
+                };
+
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '"' || c == ']' {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                while chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+                    chars.next();
+                }
+                if name.is_empty() || chars.next() != Some('"') {
+                    return Err(syntax("malformed tag pair"));
+                }
+
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if c != '\n' => value.push(c),
+                            _ => return Err(syntax("unterminated tag value")),
+                        },
+                        Some('\n') | None => return Err(syntax("unterminated tag value")),
+                        Some(c) => value.push(c),
+                    }
+                }
+                while chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+                    chars.next();
+                }
+                if chars.next() != Some(']') {
+                    return Err(syntax("malformed tag pair"));
+                }
+                tokens.push((Token::Tag(name, value), start));
+            },
+            '(' => tokens.push((Token::Open, start)),
+            ')' => tokens.push((Token::Close, start)),
+            '.' => {},
+            '*' => tokens.push((Token::Result(String::from("*")), start)),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                    chars.next();
+                }
+                match digits.parse() {
+                    Ok(nag) => tokens.push((Token::Nag(nag), start)),
+                    Err(_) => return Err(PgnError::Syntax {
+                        line: start,
+                        message: format!("invalid NAG '${digits}'"),
+                    }),
+                }
+            },
+            '!' | '?' => {
+                let mut suffix = String::from(c);
+                while let Some(&c) = chars.peek().filter(|c| **c == '!' || **c == '?') {
+                    suffix.push(c);
+                    chars.next();
+                }
+                let nag = match suffix.as_str() {
+                    "!" => 1,
+                    "?" => 2,
+                    "!!" => 3,
+                    "??" => 4,
+                    "!?" => 5,
+                    "?!" => 6,
+                    _ => return Err(PgnError::Syntax {
+                        line: start,
+                        message: format!("invalid annotation '{suffix}'"),
+                    }),
+                };
+                tokens.push((Token::Nag(nag), start));
+            },
+            c if c.is_ascii_alphanumeric() => {
+                let mut symbol = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || "_+#=:-/".contains(c)) {
+                        break;
+                    }
+                    symbol.push(c);
+                    chars.next();
+                }
+                let token = match symbol.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" => Token::Result(symbol),
+                    _ => Token::Symbol(symbol),
+                };
+                tokens.push((token, start));
+            },
+            c => return Err(PgnError::Syntax {
+                line: start,
+                message: format!("unexpected character '{c}'"),
+            }),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Read every game of a PGN text, checking each move against the move
+/// generator.
+pub fn parse_pgn(text: &str) -> Result<Vec<Game>, PgnError> {
+    let tokens = tokenize(text)?;
+    let mut games = Vec::new();
+    let mut pos = 0;
+    while pos < tokens.len() {
+        games.push(parse_game(&tokens, &mut pos)?);
+    }
+    Ok(games)
+}
+
+impl FromStr for Game {
+    type Err = PgnError;
+
+    /// Read the first game of a PGN text.
+    fn from_str(text: &str) -> Result<Game, PgnError> {
+        match parse_pgn(text)?.into_iter().next() {
+            Some(game) => Ok(game),
+            None => Err(PgnError::Syntax { line: 1, message: String::from("no game") }),
+        }
+    }
+}
+
+fn parse_game(tokens: &[(Token, usize)], pos: &mut usize) -> Result<Game, PgnError> {
+    let mut tags = Vec::new();
+    let mut start = Board::start();
+    while let Some((Token::Tag(name, value), line)) = tokens.get(*pos) {
+        if name == "FEN" {
+            start = Board::new(value).map_err(|error| PgnError::Fen { line: *line, error })?;
+        }
+        tags.push((name.clone(), value.clone()));
+        *pos += 1;
+    }
+
+    let line = parse_line(tokens, pos, start.clone(), 0, false)?;
+
+    let result = match tokens.get(*pos) {
+        Some((Token::Result(result), _)) => {
+            *pos += 1;
+            result.clone()
+        },
+        _ => tags
+            .iter()
+            .find(|(name, _)| name == "Result")
+            .map_or(String::from("*"), |(_, value)| value.clone()),
+    };
+
+    Ok(Game { tags, start, line, result })
+}
+
+/// Read moves played from `board` up to the end of the game, or the closing
+/// parenthesis of a variation when `nested`.
+fn parse_line(
+    tokens: &[(Token, usize)],
+    pos: &mut usize,
+    mut board: Board,
+    mut ply: usize,
+    nested: bool,
+) -> Result<Line, PgnError> {
+    let mut line = Line::default();
+    // position before the last move, where its variations start
+    let mut before: Option<Board> = None;
+
+    loop {
+        let Some((token, at)) = tokens.get(*pos) else {
+            if nested {
+                let line = tokens.last().map_or(1, |(_, line)| *line);
+                return Err(PgnError::Syntax { line, message: String::from("unterminated variation") });
+            }
+            break;
+        };
+        let syntax = |message: &str| PgnError::Syntax { line: *at, message: message.to_string() };
+
+        match token {
+            Token::Comment(text) => {
+                let comment = match line.moves.last_mut() {
+                    Some(last) => &mut last.comment,
+                    None => &mut line.comment,
+                };
+                match comment {
+                    Some(comment) => {
+                        comment.push(' ');
+                        *comment += text;
+                    },
+                    None => *comment = Some(text.clone()),
+                }
+            },
+            Token::Nag(nag) => match line.moves.last_mut() {
+                Some(last) => last.nags.push(*nag),
+                None => return Err(syntax("annotation before any move")),
+            },
+            Token::Open => {
+                let Some(before) = &before else {
+                    return Err(syntax("variation before any move"));
+                };
+                *pos += 1;
+                let variation = parse_line(tokens, pos, before.clone(), ply - 1, true)?;
+                if let Some(last) = line.moves.last_mut() {
+                    last.variations.push(variation);
+                }
+                continue;
+            },
+            Token::Close => {
+                if !nested {
+                    return Err(syntax("unmatched ')'"));
+                }
+                *pos += 1;
+                break;
+            },
+            Token::Result(_) | Token::Tag(..) => {
+                if nested {
+                    return Err(syntax("unterminated variation"));
+                }
+                break;
+            },
+            // move number
+            Token::Symbol(symbol) if symbol.chars().all(|c| c.is_ascii_digit()) => {},
+            Token::Symbol(san) => {
+                ply += 1;
+                let mv = board.parse_san(san).map_err(|reason| PgnError::IllegalMove {
+                    line: *at,
+                    ply,
+                    san: san.clone(),
+                    reason,
+                })?;
+                before = Some(board.clone());
+                board.make_move(mv);
+                line.moves.push(PgnMove::new(mv));
+            },
+        }
+        *pos += 1;
+    }
+
+    Ok(line)
+}
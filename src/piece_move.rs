@@ -16,11 +16,14 @@ pub enum SpecialMove {
     PromotionCapture(Piece),
 }
 
-pub const NO_MOVE: Move = Move(6); // used only for placeholders
+/// Placeholder for "no move", a8a8. Origin and target are the same square,
+/// so no real move is ever encoded like this.
+pub const NO_MOVE: Move = Move(0);
 
 /// Uses 16-bit move structure from
 /// https://www.chessprogramming.org/Encoding_Moves
 /// 
+/// ```text
 ///        111111   111111  origin -> target
 /// 
 ///    0	0	0	0	0	quiet moves
@@ -37,8 +40,10 @@ pub const NO_MOVE: Move = Move(6); // used only for placeholders
 ///    13	1	1	0	1	bishop-promo capture
 ///    14	1	1	1	0	rook-promo capture
 ///    15	1	1	1	1	queen-promo capture
+/// ```
 
 #[derive(Copy, Clone, PartialEq, Debug)] 
+#[allow(clippy::tabs_in_doc_comments)]
 pub struct Move(u16);
 
 impl Move {
@@ -116,16 +121,41 @@ impl Move {
         }
     }
 
+    /// Whether the move captures a piece, en passant included.
+    #[inline]
+    pub fn is_capture(&self) -> bool {
+        self.0 & (0b0100 << 12) != 0
+    }
+
+    /// Whether the move promotes a pawn.
+    #[inline]
+    pub fn is_promotion(&self) -> bool {
+        self.0 & (0b1000 << 12) != 0
+    }
+
 }
 
 
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write! {
-            f,
-            "{}{}",
-            self.get_origin(),
-            self.get_target(),
+        // UCI null move
+        if *self == NO_MOVE {
+            return write!(f, "0000");
+        }
+        write!(f, "{}{}", self.get_origin(), self.get_target())?;
+
+        // promotions name the piece in lowercase, like e7e8q
+        match self.get_special() {
+            SpecialMove::Promotion(p) | SpecialMove::PromotionCapture(p) => {
+                let letter = match p {
+                    Piece::Queen  => "q",
+                    Piece::Rook   => "r",
+                    Piece::Bishop => "b",
+                    _             => "n",
+                };
+                write!(f, "{letter}")
+            },
+            _ => Ok(()),
         }
     }
 }
@@ -155,11 +185,30 @@ impl MoveList {
 
     /// Get last move without changing list.
     pub fn peek(&self) -> Option<Move> {
-        if self.0.is_empty() {
-            None
-        } else {
-            Some(self.0[self.0.len()])
-        }
+        self.0.last().copied()
+    }
+
+    /// Get number of moves in the list.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if the list has no moves.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check if the list contains a move.
+    pub fn contains(&self, mv: Move) -> bool {
+        self.0.contains(&mv)
+    }
+
+    /// Iterate over the moves without copying the list.
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.0.iter()
     }
 
     /// Get actual Vec to iterate over
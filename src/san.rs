@@ -0,0 +1,217 @@
+// san.rs
+
+use std::fmt;
+use crate::board::Board;
+use crate::piece::Piece;
+use crate::piece_move::*;
+use crate::square::Square;
+
+/// Reasons a SAN move can't be read in a position.
+#[derive(Debug, PartialEq)]
+pub enum SanParseError {
+    /// The text is not a move in standard algebraic notation.
+    Syntax(String),
+    /// No legal move matches.
+    Illegal(String),
+    /// More than one legal move matches, the move needs disambiguation.
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanParseError::Syntax(san)    => write!(f, "'{san}' is not a SAN move"),
+            SanParseError::Illegal(san)   => write!(f, "'{san}' is not a legal move"),
+            SanParseError::Ambiguous(san) => write!(f, "'{san}' is ambiguous"),
+        }
+    }
+}
+
+impl std::error::Error for SanParseError {}
+
+/// SAN letter of a piece, empty for pawns.
+fn piece_letter(piece: Piece) -> &'static str {
+    match piece {
+        Piece::King   => "K",
+        Piece::Queen  => "Q",
+        Piece::Rook   => "R",
+        Piece::Bishop => "B",
+        Piece::Knight => "N",
+        Piece::Pawn   => "",
+    }
+}
+
+impl Board {
+
+    /// Type of the piece standing on `square`.
+    fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.get_bitboard_index_at_square(square)
+            .and_then(|index| Piece::from_index(index as u8))
+    }
+
+    /// Write a legal move in standard algebraic notation, like `Nbd7`,
+    /// `exd5`, `e8=Q+` or `O-O-O#`.
+    pub fn move_to_san(&self, mv: Move) -> String {
+        let origin = mv.get_origin();
+        let target = mv.get_target();
+
+        let mut san = match mv.get_special() {
+            SpecialMove::KingsideCastle => String::from("O-O"),
+            SpecialMove::QueensideCastle => String::from("O-O-O"),
+            special => {
+                let piece = self.piece_at(origin).unwrap_or(Piece::Pawn);
+                let mut san = String::from(piece_letter(piece));
+
+                if piece == Piece::Pawn {
+                    if mv.is_capture() {
+                        san.push((b'a' + origin.file()) as char);
+                    }
+                } else {
+                    // other pieces of the same type that can reach the target
+                    let rivals: Vec<Square> = self
+                        .legal_moves()
+                        .iter()
+                        .filter(|other| {
+                            other.get_target() == target &&
+                            other.get_origin() != origin &&
+                            self.piece_at(other.get_origin()) == Some(piece)
+                        })
+                        .map(|other| other.get_origin())
+                        .collect();
+
+                    if !rivals.is_empty() {
+                        let same_file = rivals.iter().any(|sq| sq.file() == origin.file());
+                        let same_rank = rivals.iter().any(|sq| sq.rank() == origin.rank());
+                        if !same_file {
+                            san.push((b'a' + origin.file()) as char);
+                        } else if !same_rank {
+                            san.push((b'0' + origin.rank()) as char);
+                        } else {
+                            san += &origin.to_string();
+                        }
+                    }
+                }
+
+                if mv.is_capture() {
+                    san.push('x');
+                }
+                san += &target.to_string();
+
+                if let SpecialMove::Promotion(p) | SpecialMove::PromotionCapture(p) = special {
+                    san.push('=');
+                    san += piece_letter(p);
+                }
+                san
+            },
+        };
+
+        let mut after = self.clone();
+        if after.make_move(mv).is_some() && after.is_in_check() {
+            if after.legal_moves().is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+        san
+    }
+
+    /// Read a move in standard algebraic notation. Check and annotation
+    /// suffixes are ignored, and castling may be written with zeros.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanParseError> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal = self.legal_moves();
+
+        // castling
+        let castle = match text {
+            "O-O" | "0-0" => Some(SpecialMove::KingsideCastle),
+            "O-O-O" | "0-0-0" => Some(SpecialMove::QueensideCastle),
+            _ => None,
+        };
+        if let Some(castle) = castle {
+            return legal
+                .iter()
+                .copied()
+                .find(|mv| mv.get_special() == castle)
+                .ok_or(SanParseError::Illegal(san.to_string()));
+        }
+
+        let syntax = || SanParseError::Syntax(san.to_string());
+        let mut chars: Vec<char> = text.chars().collect();
+
+        // promotion, written `e8=Q` or `e8Q`
+        let mut promotion = None;
+        if let Some(&last) = chars.last() {
+            let piece = match last {
+                'Q' => Some(Piece::Queen),
+                'R' => Some(Piece::Rook),
+                'B' => Some(Piece::Bishop),
+                'N' => Some(Piece::Knight),
+                _ => None,
+            };
+            if piece.is_some() && chars.len() > 2 {
+                promotion = piece;
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        let piece = match chars.first() {
+            Some('K') => Piece::King,
+            Some('Q') => Piece::Queen,
+            Some('R') => Piece::Rook,
+            Some('B') => Piece::Bishop,
+            Some('N') => Piece::Knight,
+            Some(_) => Piece::Pawn,
+            None => return Err(syntax()),
+        };
+        let rest = if piece == Piece::Pawn { &chars[..] } else { &chars[1..] };
+
+        // target square last, optional disambiguation and capture before it
+        if rest.len() < 2 {
+            return Err(syntax());
+        }
+        let target_text: String = rest[rest.len() - 2..].iter().collect();
+        let target = Square::from_alg(&target_text).ok_or_else(syntax)?;
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in &rest[..rest.len() - 2] {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(c as u8 - b'a')
+                },
+                '1'..='8' if from_rank.is_none() => from_rank = Some(c as u8 - b'0'),
+                'x' => {},
+                _ => return Err(syntax()),
+            }
+        }
+
+        let matches: Vec<Move> = legal
+            .iter()
+            .copied()
+            .filter(|mv| {
+                let origin = mv.get_origin();
+                let promoted = match mv.get_special() {
+                    SpecialMove::Promotion(p) | SpecialMove::PromotionCapture(p) => Some(p),
+                    _ => None,
+                };
+                mv.get_target() == target &&
+                !matches!(mv.get_special(),
+                    SpecialMove::KingsideCastle | SpecialMove::QueensideCastle) &&
+                self.piece_at(origin) == Some(piece) &&
+                promoted == promotion &&
+                from_file.is_none_or(|file| origin.file() == file) &&
+                from_rank.is_none_or(|rank| origin.rank() == rank)
+            })
+            .collect();
+
+        match matches.len() {
+            0 => Err(SanParseError::Illegal(san.to_string())),
+            1 => Ok(matches[0]),
+            _ => Err(SanParseError::Ambiguous(san.to_string())),
+        }
+    }
+}
@@ -1,112 +1,320 @@
 // sliding_attacks.rs
 
 use crate::bitboard::*;
-use crate::square::Square;
+use crate::square::{Square, ALL_SQUARES};
 
-/// Generate bishop rays at a square for the current board blockers.
-pub fn get_bishop_rays(square: Square, blockers: Bitboard) -> Bitboard {
-    let mut attacks = EMPTY;
+/// Relevant blocker squares for each square, edges excluded.
+pub static mut BISHOP_MASKS: [Bitboard; 64] = [EMPTY; 64];
+pub static mut ROOK_MASKS: [Bitboard; 64] = [EMPTY; 64];
 
-    // SW
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_sw() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
-    }
+/// Attack lookup tables indexed by square then magic index.
+pub static mut BISHOP_ATTACKS: [[Bitboard; 512]; 64] = [[EMPTY; 512]; 64];
+pub static mut ROOK_ATTACKS: [[Bitboard; 4096]; 64] = [[EMPTY; 4096]; 64];
 
-    // SE
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_se() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
-    }
+const BISHOP_RELEVANT_BITS: [u8; 64] = [
+    6, 5, 5, 5, 5, 5, 5, 6,
+    5, 5, 5, 5, 5, 5, 5, 5,
+    5, 5, 7, 7, 7, 7, 5, 5,
+    5, 5, 7, 9, 9, 7, 5, 5,
+    5, 5, 7, 9, 9, 7, 5, 5,
+    5, 5, 7, 7, 7, 7, 5, 5,
+    5, 5, 5, 5, 5, 5, 5, 5,
+    6, 5, 5, 5, 5, 5, 5, 6,
+];
+
+const ROOK_RELEVANT_BITS: [u8; 64] = [
+    12, 11, 11, 11, 11, 11, 11, 12,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    11, 10, 10, 10, 10, 10, 10, 11,
+    12, 11, 11, 11, 11, 11, 11, 12,
+];
+
+/// Magic multipliers mapping every blocker subset of a mask to a table slot
+/// holding the matching attacks. Found offline by random search.
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0032482800818200, 0x0819022820450000,
+    0x4242108204880008, 0x8044404080410224,
+    0x1407104000080100, 0x1B01100210004000,
+    0x0B0C0888841010C1, 0x08A0108201104020,
+    0x00003888654C0410, 0x0010220202520A00,
+    0x4003048400820001, 0x1007022082000002,
+    0x3C22040420040A18, 0x3440508820080030,
+    0x1204208090082100, 0x0000930501012000,
+    0x0010842002D00100, 0x0202000404484208,
+    0x0008000100410602, 0x0088000404200800,
+    0xA004100202020232, 0x0004200100A01002,
+    0x0201000208020200, 0x2022010022020200,
+    0x80100410100410B0, 0x3010704848011100,
+    0x2000380010048320, 0x8068080000202120,
+    0x0001010080104000, 0x4808004120806000,
+    0x5000821000823008, 0x0004010000504224,
+    0x0088044010040808, 0x0812022000032814,
+    0xA000280800110202, 0x2882200802010105,
+    0x0508082400024100, 0x8001010200030800,
+    0x4001260E00019820, 0x0C02040308507180,
+    0x0880884441009000, 0x20A84202A0011000,
+    0x00010410820C1000, 0x4000002018000108,
+    0x0080091124004A00, 0x0409010302010700,
+    0x0088128802040040, 0x1801010408801100,
+    0x0184010190110800, 0x0000404208210000,
+    0x0000120908880000, 0x9006180020884020,
+    0x900000400488410A, 0x0410218401020100,
+    0x0004D00428008100, 0x0002021404008400,
+    0x0911410150222026, 0x0300202088041040,
+    0x0100A06124122800, 0x001C042000420200,
+    0x0004018208030400, 0x0044040405080200,
+    0x040820200402B680, 0x0204200411020410,
+];
 
-    // NE
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_ne() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
+const ROOK_MAGICS: [u64; 64] = [
+    0x0080008040002018, 0x0040100040002001,
+    0x09000D0010200040, 0x8080080010008004,
+    0xA280080002340080, 0x2500050024000208,
+    0x0280010000800200, 0x1100144380220100,
+    0xA92080048C204002, 0x0802804003200080,
+    0x0108802000100089, 0x8060800800801002,
+    0xA002000822000410, 0x950A001002000824,
+    0x1202000408010200, 0x02950008408A0100,
+    0x40A0A18000814000, 0x0080848020004011,
+    0x0800828010002000, 0x0500420010200A00,
+    0x0008818004000802, 0x0000808004000200,
+    0x0000040001020810, 0x0081120000442081,
+    0x0522400180002090, 0x2800200040401000,
+    0x402004A180100481, 0x0880420200200810,
+    0x000A040080800800, 0x080A000404001020,
+    0x0000010400820810, 0x4000D08200004C09,
+    0x0100804000800020, 0xCC01028026004200,
+    0x0081002001004010, 0x0000800800801000,
+    0x0009800401802800, 0x0002000902000410,
+    0x0000180144001022, 0x00008100C6002884,
+    0x1200800040008024, 0x2000200050044000,
+    0x0090882200420010, 0x6A1810200A020040,
+    0x0480040008008080, 0x0206001108160014,
+    0xC010040200010100, 0x000001018046000C,
+    0x00B0400880042080, 0x0040002010080220,
+    0x0020004228110100, 0x0150000804004140,
+    0x0028008004000980, 0x0801000804000300,
+    0x2C80210208900400, 0x0000040130408200,
+    0x4001409100258001, 0x0005004002883021,
+    0x019041E003001019, 0x8000200900041001,
+    0x4021001002040801, 0x0011000400080201,
+    0x1600010210408804, 0x2010810084003042,
+];
+
+const BISHOP_DIRECTIONS: [fn(&Square) -> Option<Square>; 4] = [
+    Square::due_sw,
+    Square::due_se,
+    Square::due_ne,
+    Square::due_nw,
+];
+
+const ROOK_DIRECTIONS: [fn(&Square) -> Option<Square>; 4] = [
+    Square::due_n,
+    Square::due_s,
+    Square::due_w,
+    Square::due_e,
+];
+
+/// Walk each ray from `square` in the given directions until a blocker or
+/// the edge of the board, blockers included.
+fn walk_rays(
+    square: Square,
+    blockers: Bitboard,
+    directions: &[fn(&Square) -> Option<Square>],
+) -> Bitboard {
+    let mut attacks = EMPTY;
+    for step in directions {
+        let mut sq = square;
+        loop {
+            sq = match step(&sq) {
+                Some(s) => s,
+                None => break
+            };
+            attacks |= Bitboard::from_square(sq);
+            if blockers.get_bit_by_square(sq) == 1 { break }
+        }
     }
+    attacks
+}
 
-    // NW
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_nw() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
+/// Walk each ray from `square` in the given directions, leaving out the last
+/// square before the edge since a piece there never blocks anything.
+#[allow(clippy::while_let_loop)]
+fn walk_mask(
+    square: Square,
+    directions: &[fn(&Square) -> Option<Square>],
+) -> Bitboard {
+    let mut mask = EMPTY;
+    for step in directions {
+        let mut sq = square;
+        loop {
+            let next = match step(&sq) {
+                Some(s) => s,
+                None => break
+            };
+            if step(&next).is_none() { break }
+            mask |= Bitboard::from_square(next);
+            sq = next;
+        }
     }
+    mask
+}
 
-    attacks
+/// Generate bishop rays by walking the board square by square. Only used to
+/// fill the lookup tables.
+fn walk_bishop_rays(square: Square, blockers: Bitboard) -> Bitboard {
+    walk_rays(square, blockers, &BISHOP_DIRECTIONS)
 }
 
+/// Generate rook rays by walking the board square by square. Only used to
+/// fill the lookup tables.
+fn walk_rook_rays(square: Square, blockers: Bitboard) -> Bitboard {
+    walk_rays(square, blockers, &ROOK_DIRECTIONS)
+}
 
-/// Generate rook rays at a square for the current board blockers.
-pub fn get_rook_rays(square: Square, blockers: Bitboard) -> Bitboard {
-    let mut attacks = EMPTY;
+#[inline]
+fn magic_index(blockers: Bitboard, mask: Bitboard, magic: u64, bits: u8) -> usize {
+    ((blockers & mask).to_int().wrapping_mul(magic) >> (64 - bits)) as usize
+}
 
-    // N
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_n() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
-    }
+/// Fill the masks and attack tables for both sliders. Called from
+/// `gen_tables`.
+pub fn gen_sliding_attacks() {
+    for square in ALL_SQUARES {
+        let sq = square.to_index();
+        let bishop_mask = walk_mask(square, &BISHOP_DIRECTIONS);
+        let rook_mask = walk_mask(square, &ROOK_DIRECTIONS);
 
-    // S
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_s() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
-    }
+        unsafe {
+            BISHOP_MASKS[sq] = bishop_mask;
+            ROOK_MASKS[sq] = rook_mask;
+        }
 
-    // W
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_w() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
+        // enumerate every subset of the mask (carry rippler)
+        let mut subset = 0u64;
+        loop {
+            let blockers = Bitboard::new(subset);
+            let i = magic_index(
+                blockers, bishop_mask, BISHOP_MAGICS[sq], BISHOP_RELEVANT_BITS[sq]);
+            unsafe {
+                BISHOP_ATTACKS[sq][i] = walk_bishop_rays(square, blockers);
+            }
+            subset = subset.wrapping_sub(bishop_mask.to_int()) & bishop_mask.to_int();
+            if subset == 0 { break }
+        }
+
+        let mut subset = 0u64;
+        loop {
+            let blockers = Bitboard::new(subset);
+            let i = magic_index(
+                blockers, rook_mask, ROOK_MAGICS[sq], ROOK_RELEVANT_BITS[sq]);
+            unsafe {
+                ROOK_ATTACKS[sq][i] = walk_rook_rays(square, blockers);
+            }
+            subset = subset.wrapping_sub(rook_mask.to_int()) & rook_mask.to_int();
+            if subset == 0 { break }
+        }
     }
+}
 
-    // E
-    let mut sq = square.clone();
-    loop {
-        sq = match sq.due_e() {
-            Some(s) => s,
-            None => break
-        };
-        attacks |= Bitboard::from_square(sq);
-        if blockers.get_bit_by_square(sq) == 1 { break }
+/// Get bishop rays at a square for the current board blockers.
+#[inline]
+pub fn get_bishop_rays(square: Square, blockers: Bitboard) -> Bitboard {
+    let sq = square.to_index();
+    unsafe {
+        let i = magic_index(
+            blockers, BISHOP_MASKS[sq], BISHOP_MAGICS[sq], BISHOP_RELEVANT_BITS[sq]);
+        BISHOP_ATTACKS[sq][i]
     }
+}
 
-    attacks
+/// Get rook rays at a square for the current board blockers.
+#[inline]
+pub fn get_rook_rays(square: Square, blockers: Bitboard) -> Bitboard {
+    let sq = square.to_index();
+    unsafe {
+        let i = magic_index(
+            blockers, ROOK_MASKS[sq], ROOK_MAGICS[sq], ROOK_RELEVANT_BITS[sq]);
+        ROOK_ATTACKS[sq][i]
+    }
 }
 
 #[inline]
 pub fn get_queen_rays(square: Square, blockers: Bitboard) -> Bitboard {
     get_bishop_rays(square, blockers) | get_rook_rays(square, blockers)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_tables::gen_tables;
+
+    /// xorshift64* so the sample is the same on every run.
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state >> 12;
+        *state ^= *state << 25;
+        *state ^= *state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Check the lookup against the ray walk for every subset of a mask.
+    fn check_all_subsets(
+        square: Square,
+        mask: Bitboard,
+        walk: fn(Square, Bitboard) -> Bitboard,
+        lookup: fn(Square, Bitboard) -> Bitboard,
+    ) {
+        let mut subset = 0u64;
+        loop {
+            let blockers = Bitboard::new(subset);
+            assert_eq!(
+                lookup(square, blockers).to_int(),
+                walk(square, blockers).to_int(),
+                "square {square}, blockers {:x}", subset,
+            );
+            subset = subset.wrapping_sub(mask.to_int()) & mask.to_int();
+            if subset == 0 { break }
+        }
+    }
+
+    #[test]
+    fn lookup_matches_ray_walk_for_every_mask_subset() {
+        gen_tables();
+        for square in ALL_SQUARES {
+            let (bishop_mask, rook_mask) = unsafe {
+                (BISHOP_MASKS[square.to_index()], ROOK_MASKS[square.to_index()])
+            };
+            check_all_subsets(square, bishop_mask, walk_bishop_rays, get_bishop_rays);
+            check_all_subsets(square, rook_mask, walk_rook_rays, get_rook_rays);
+        }
+    }
+
+    #[test]
+    fn lookup_matches_ray_walk_for_random_blockers() {
+        gen_tables();
+        let mut state = 0xDEAD_BEEF_CAFE_F00D;
+        for _ in 0..20_000 {
+            // mix sparse and crowded boards
+            let blockers = match next_random(&mut state) % 3 {
+                0 => next_random(&mut state) & next_random(&mut state),
+                1 => next_random(&mut state),
+                _ => next_random(&mut state) | next_random(&mut state),
+            };
+            let blockers = Bitboard::new(blockers);
+            for square in ALL_SQUARES {
+                assert_eq!(
+                    get_bishop_rays(square, blockers).to_int(),
+                    walk_bishop_rays(square, blockers).to_int(),
+                );
+                assert_eq!(
+                    get_rook_rays(square, blockers).to_int(),
+                    walk_rook_rays(square, blockers).to_int(),
+                );
+            }
+        }
+    }
+}
@@ -13,6 +13,7 @@ impl Square {
     }
 
     /// Create square from agebraic notation
+    #[allow(clippy::collapsible_if)]
     pub fn from_alg(coord: &str) -> Option<Square> {
         let mut chars = coord.chars();
         let file = match chars.next() {
@@ -47,7 +48,7 @@ impl Square {
     /// Gets the square index as u8.
     #[inline] 
     pub fn to_int(&self) -> u8 {
-        self.0 as u8
+        self.0
     } 
 
     /// Gets the square file. 
@@ -64,6 +65,7 @@ impl Square {
 
     /// Gets the square one rank above. Returns none if current square is on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_n(&self) -> Option<Square> {
         if self.rank() == 8 {
             return None;
@@ -73,6 +75,7 @@ impl Square {
 
     /// Gets the square one rank below. Returns none if current square is on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_s(&self) -> Option<Square> {
         if self.rank() == 1 {
             return None;
@@ -83,6 +86,7 @@ impl Square {
     /// Gets the square one file to the left. Returns none if current square is 
     /// on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_w(&self) -> Option<Square> {
         if self.file() == 0 {
             return None;
@@ -93,6 +97,7 @@ impl Square {
     /// Gets the square one file to the right. Returns none if current square 
     /// is on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_e(&self) -> Option<Square> {
         if self.file() == 7 {
             return None;
@@ -103,6 +108,7 @@ impl Square {
     /// Gets the square diagonally north west. Returns none if current square 
     /// is on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_nw(&self) -> Option<Square> {
         if self.file() == 0 || self.rank() == 8 {
             return None;
@@ -113,6 +119,7 @@ impl Square {
     /// Gets the square diagonally north east. Returns none if current square 
     /// is on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_ne(&self) -> Option<Square> {
         if self.file() == 7 || self.rank() == 8 {
             return None;
@@ -123,6 +130,7 @@ impl Square {
     /// Gets the square diagonally south west. Returns none if current square 
     /// is on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_sw(&self) -> Option<Square> {
         if self.file() == 0 || self.rank() == 1 {
             return None;
@@ -132,6 +140,7 @@ impl Square {
 
     /// Gets the square diagonally south east. Returns none if current square is on the edge.
     #[inline]
+    #[allow(clippy::needless_return)]
     pub fn due_se(&self) -> Option<Square> {
         if self.file() == 7 || self.rank() == 1 {
             return None;
@@ -218,8 +227,8 @@ impl fmt::Display for Square {
             write! {
                 f,
                 "{}{}",
-                (('a' as u8) + self.file()) as char,
-                (('0' as u8) + self.rank()) as char,
+                (b'a' + self.file()) as char,
+                (b'0' + self.rank()) as char,
             }
         }
     }
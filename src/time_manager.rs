@@ -0,0 +1,126 @@
+// time_manager.rs
+
+use std::time::{Duration, Instant};
+use crate::color::Color;
+
+/// Time kept back from every move for GUI and communication lag, in ms,
+/// unless the `Move Overhead` option says otherwise.
+pub const DEFAULT_MOVE_OVERHEAD: u64 = 50;
+
+/// Largest `Move Overhead` accepted, in ms.
+pub const MAX_MOVE_OVERHEAD: u64 = 5000;
+
+/// Moves assumed left in the game when the GUI does not send `movestogo`.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// Limits of a single search, parsed from a UCI `go` command. Times are in
+/// milliseconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u64>,
+    pub movetime: Option<u64>,
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    /// Search until told to stop.
+    pub infinite: bool,
+    /// Search on the opponent's time until `ponderhit` or `stop`.
+    pub ponder: bool,
+    /// Look for a mate in this many moves, searching `2 * mate - 1` plies
+    /// at most.
+    pub mate: Option<u8>,
+    /// Root moves to search, in UCI notation. Empty means all of them.
+    pub searchmoves: Vec<String>,
+}
+
+impl SearchLimits {
+
+    /// Limits for a fixed depth search.
+    pub fn depth(depth: u8) -> SearchLimits {
+        SearchLimits { depth: Some(depth), ..SearchLimits::default() }
+    }
+
+    /// Limits for a fixed time search.
+    pub fn movetime(ms: u64) -> SearchLimits {
+        SearchLimits { movetime: Some(ms), ..SearchLimits::default() }
+    }
+}
+
+/// Decides how long the current search may run.
+///
+/// The soft budget is checked between iterations, a new iteration is not
+/// started once it is used up. The hard budget is checked during search and
+/// aborts it.
+pub struct TimeManager {
+    start: Instant,
+    soft: Option<Duration>,
+    hard: Option<Duration>,
+}
+
+impl TimeManager {
+
+    /// Allocate time for `color` to move under `limits`, keeping `overhead`
+    /// ms back for lag.
+    pub fn new(limits: &SearchLimits, color: Color, overhead: u64) -> TimeManager {
+        let start = Instant::now();
+
+        if let Some(movetime) = limits.movetime {
+            let budget = Duration::from_millis(
+                movetime.saturating_sub(overhead).max(1));
+            return TimeManager { start, soft: Some(budget), hard: Some(budget) };
+        }
+
+        let (time, inc) = match color {
+            Color::WHITE => (limits.wtime, limits.winc.unwrap_or(0)),
+            Color::BLACK => (limits.btime, limits.binc.unwrap_or(0)),
+        };
+        let time = match time {
+            Some(t) => t.saturating_sub(overhead).max(1),
+            None => return TimeManager { start, soft: None, hard: None },
+        };
+
+        let moves_to_go = limits.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+        // never plan to use more than most of what is left on the clock
+        let max = time * 8 / 10;
+        let soft = (time / moves_to_go + inc * 3 / 4).min(max).max(1);
+        let hard = (soft * 3).min(max).max(soft);
+
+        TimeManager {
+            start,
+            soft: Some(Duration::from_millis(soft)),
+            hard: Some(Duration::from_millis(hard)),
+        }
+    }
+
+    /// Start the budget over from now, when a ponder search becomes a real
+    /// one on `ponderhit`.
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    /// Time since the search started.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Whether there is no time left to start another iteration.
+    pub fn soft_exceeded(&self) -> bool {
+        match self.soft {
+            Some(soft) => self.elapsed() >= soft,
+            None => false,
+        }
+    }
+
+    /// Whether the search has to stop right now.
+    pub fn hard_exceeded(&self) -> bool {
+        match self.hard {
+            Some(hard) => self.elapsed() >= hard,
+            None => false,
+        }
+    }
+}
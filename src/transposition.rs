@@ -0,0 +1,117 @@
+// transposition.rs
+
+use std::mem::size_of;
+use crate::piece_move::{Move, NO_MOVE};
+
+/// Default transposition table size in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Smallest and largest sizes accepted for the UCI `Hash` option.
+pub const MIN_HASH_MB: usize = 1;
+pub const MAX_HASH_MB: usize = 1024;
+
+/// How a stored score relates to the true score of the position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    /// Score is exact, it fell strictly inside the search window.
+    Exact,
+    /// Search failed high, true score is at least this.
+    Lower,
+    /// Search failed low, true score is at most this.
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TTEntry {
+    pub key: u64,
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Move,
+}
+
+/// Fixed size hash table of previously searched positions, indexed by the
+/// Zobrist hash of the board.
+pub struct TranspositionTable {
+    entries: Vec<Option<TTEntry>>,
+    mask: usize,
+}
+
+impl TranspositionTable {
+
+    /// Create a table using roughly `mb` megabytes. The number of entries is
+    /// rounded down to a power of two so lookups can mask the key.
+    pub fn new(mb: usize) -> TranspositionTable {
+        let bytes = mb.clamp(MIN_HASH_MB, MAX_HASH_MB) * 1024 * 1024;
+        let wanted = bytes / size_of::<Option<TTEntry>>();
+        let len = 1usize << (usize::BITS - 1 - wanted.leading_zeros());
+
+        TranspositionTable {
+            entries: vec![None; len],
+            mask: len - 1,
+        }
+    }
+
+    /// Throw away the table and allocate one of a new size.
+    pub fn resize(&mut self, mb: usize) {
+        *self = TranspositionTable::new(mb);
+    }
+
+    /// Forget every stored position.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|e| *e = None);
+    }
+
+    /// Number of entry slots in the table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Look up a position, returning the entry only if the full key matches.
+    #[inline]
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        match self.entries[key as usize & self.mask] {
+            Some(entry) if entry.key == key => Some(entry),
+            _ => None,
+        }
+    }
+
+    /// Store a search result. An existing entry for the same position is only
+    /// overwritten by a search of at least the same depth, entries for other
+    /// positions are always replaced.
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: u8,
+        score: i32,
+        bound: Bound,
+        best_move: Move,
+    ) {
+        let slot = &mut self.entries[key as usize & self.mask];
+        let mut best_move = best_move;
+        if let Some(old) = slot {
+            if old.key == key {
+                if old.depth > depth {
+                    return;
+                }
+                // keep the old best move if this search did not find one
+                if best_move == NO_MOVE {
+                    best_move = old.best_move;
+                }
+            }
+        }
+
+        *slot = Some(TTEntry { key, depth, score, bound, best_move });
+    }
+
+    /// Permille of the first thousand slots in use, reported as `hashfull`.
+    pub fn hashfull(&self) -> usize {
+        let sample = self.entries.len().min(1000);
+        self.entries[..sample].iter().filter(|e| e.is_some()).count() * 1000
+            / sample
+    }
+}
@@ -0,0 +1,67 @@
+// zobrist.rs
+
+use crate::square::Square;
+
+pub static mut PIECE_KEYS: [[u64; 64]; 12] = [[0; 64]; 12];
+pub static mut CASTLING_KEYS: [u64; 16] = [0; 16];
+pub static mut EN_PASSANT_KEYS: [u64; 8] = [0; 8];
+pub static mut SIDE_KEY: u64 = 0;
+
+/// Fixed seed so hashes are reproducible between runs.
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// xorshift64* pseudo random number generator.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state >> 12;
+    *state ^= *state << 25;
+    *state ^= *state >> 27;
+    state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+}
+
+/// Fill the Zobrist key tables. Called from `gen_tables`.
+#[allow(clippy::needless_range_loop)]
+pub fn gen_keys() {
+    let mut state = SEED;
+    unsafe {
+        for piece in 0..12 {
+            for sq in 0..64 {
+                PIECE_KEYS[piece][sq] = next_random(&mut state);
+            }
+        }
+        for rights in 0..16 {
+            CASTLING_KEYS[rights] = next_random(&mut state);
+        }
+        for file in 0..8 {
+            EN_PASSANT_KEYS[file] = next_random(&mut state);
+        }
+        SIDE_KEY = next_random(&mut state);
+    }
+}
+
+/// Key for the piece of bitboard index `index` standing on `square`.
+#[inline]
+pub fn piece_key(index: usize, square: Square) -> u64 {
+    unsafe { PIECE_KEYS[index][square.to_index()] }
+}
+
+/// Key for a set of castling rights.
+#[inline]
+pub fn castling_key(rights: u8) -> u64 {
+    unsafe { CASTLING_KEYS[(rights & 0b1111) as usize] }
+}
+
+/// Key for the en passant square, zero if there is none.
+#[inline]
+pub fn en_passant_key(square: Square) -> u64 {
+    if square == Square::NO_SQUARE {
+        0
+    } else {
+        unsafe { EN_PASSANT_KEYS[square.file() as usize] }
+    }
+}
+
+/// Key toggled whenever black is to move.
+#[inline]
+pub fn side_key() -> u64 {
+    unsafe { SIDE_KEY }
+}
@@ -0,0 +1,30 @@
+// book.rs
+
+use linkus_chess::book::Book;
+use linkus_chess::Board;
+
+const GAMES: &str = "[Event \"a\"]\n\n1. e4 e5 2. Nf3 Nc6 *\n\n\
+                     [Event \"b\"]\n\n1. d4 d5 2. c4 *\n\n\
+                     [Event \"c\"]\n\n1. e4 e5 2. Bc4 *\n\n\
+                     [Event \"d\"]\n\n1. e4 c5 *\n";
+
+#[test]
+fn plays_the_most_played_move() {
+    let book = Book::from_pgn(GAMES).unwrap();
+    assert_eq!(book.len(), 6);
+
+    let board = Board::start();
+    assert_eq!(book.probe(&board).unwrap().to_string(), "e2e4");
+
+    let board: Board = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".parse().unwrap();
+    assert_eq!(book.probe(&board).unwrap().to_string(), "e7e5");
+
+    let board: Board = "rnbqkbnr/pppppppp/8/8/8/P7/1PPPPPPP/RNBQKBNR b KQkq - 0 1".parse().unwrap();
+    assert!(book.probe(&board).is_none());
+}
+
+#[test]
+fn rejects_broken_games() {
+    assert!(Book::from_pgn("1. e4 e5 2. Ke3 *").is_err());
+    assert!(Book::from_pgn("").unwrap().is_empty());
+}
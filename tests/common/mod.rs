@@ -0,0 +1,13 @@
+// common/mod.rs
+
+use linkus_chess::{Board, Move};
+
+/// Legal move of `board` written `uci`, e.g. `e2e4` or `a7a8q`.
+pub fn find_move(board: &Board, uci: &str) -> Move {
+    board
+        .legal_moves()
+        .into_vec()
+        .into_iter()
+        .find(|mv| mv.to_string() == uci)
+        .unwrap_or_else(|| panic!("{uci} is not legal"))
+}
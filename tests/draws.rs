@@ -0,0 +1,123 @@
+// draws.rs
+
+mod common;
+
+use linkus_chess::{gen_tables, Board};
+use common::find_move;
+
+fn play(board: &mut Board, moves: &str) {
+    for uci in moves.split_whitespace() {
+        let mv = find_move(board, uci);
+        board.make_move(mv).unwrap();
+    }
+}
+
+#[test]
+fn threefold_repetition() {
+    gen_tables();
+    let mut board = Board::start();
+
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(board.repetitions(), 1);
+    assert!(!board.is_repetition());
+
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(board.repetitions(), 2);
+    assert!(board.is_repetition());
+}
+
+#[test]
+fn irreversible_moves_end_repetitions() {
+    gen_tables();
+    let mut board = Board::start();
+
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8 e2e3");
+    assert_eq!(board.repetitions(), 0);
+
+    // the position after e2e3 repeats, the ones before it can't
+    play(&mut board, "g8f6 g1f3 f6g8 f3g1");
+    assert_eq!(board.repetitions(), 1);
+}
+
+#[test]
+fn null_moves_end_repetitions() {
+    gen_tables();
+    let mut board = Board::start();
+    play(&mut board, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(board.repetitions(), 1);
+
+    // a null move each way lands back on positions from before them
+    let first = board.make_null_move();
+    let mv = find_move(&board, "g8f6");
+    let undo = board.make_move(mv).unwrap();
+    let second = board.make_null_move();
+    assert_eq!(board.repetitions(), 0);
+    let back = find_move(&board, "f6g8");
+    let back_undo = board.make_move(back).unwrap();
+    assert_eq!(board.repetitions(), 0);
+    assert!(!board.is_repetition());
+
+    board.unmake_move(back, back_undo);
+    board.unmake_null_move(second);
+    board.unmake_move(mv, undo);
+    board.unmake_null_move(first);
+    assert_eq!(board.repetitions(), 1);
+    assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3");
+}
+
+#[test]
+fn unmake_restores_history_and_counters() {
+    gen_tables();
+    let mut board = Board::start();
+    play(&mut board, "g1f3 g8f6 f3g1");
+
+    let mv = find_move(&board, "f6g8");
+    let undo = board.make_move(mv).unwrap();
+    assert_eq!(board.repetitions(), 1);
+    assert_eq!(board.get_fullmove_number(), 3);
+
+    board.unmake_move(mv, undo);
+    assert_eq!(board.repetitions(), 0);
+    assert_eq!(board.get_fullmove_number(), 2);
+    assert_eq!(board.get_halfmove_clock(), 3);
+}
+
+#[test]
+fn fifty_move_rule() {
+    gen_tables();
+    let mut board = Board::new("8/8/4k3/8/8/4K3/8/R7 w - - 99 80").unwrap();
+    assert!(!board.is_fifty_move_draw());
+
+    play(&mut board, "a1a2");
+    assert!(board.is_fifty_move_draw());
+
+    // mate on the hundredth ply is still mate
+    let mut mated = Board::new("k7/8/1K6/8/8/8/8/7R w - - 99 80").unwrap();
+    play(&mut mated, "h1h8");
+    assert!(!mated.is_fifty_move_draw());
+}
+
+#[test]
+fn insufficient_material() {
+    gen_tables();
+    let drawn = [
+        "8/8/4k3/8/8/4K3/8/8 w - - 0 1",
+        "8/8/4k3/8/8/4K3/8/6N1 w - - 0 1",
+        "8/8/4k3/8/8/4K3/8/5B2 w - - 0 1",
+        "8/8/4k3/3b4/8/4K3/8/5B2 w - - 0 1",
+    ];
+    for fen in drawn {
+        assert!(Board::new(fen).unwrap().is_insufficient_material(), "{fen}");
+    }
+
+    let playable = [
+        "8/8/4k3/8/8/4K3/8/4B1N1 w - - 0 1",
+        "8/8/4k3/2b5/8/4K3/8/5B2 w - - 0 1",
+        "8/8/4k3/8/8/4K3/8/5NN1 w - - 0 1",
+        "8/8/4k3/8/8/4K3/4P3/8 w - - 0 1",
+        "8/8/4k3/8/8/4K3/8/R7 w - - 0 1",
+    ];
+    for fen in playable {
+        assert!(!Board::new(fen).unwrap().is_insufficient_material(), "{fen}");
+    }
+}
@@ -0,0 +1,93 @@
+// epd.rs
+
+use linkus_chess::epd::{bench_epd, parse_epd, Epd, EpdError};
+use linkus_chess::san::SanParseError;
+use linkus_chess::time_manager::SearchLimits;
+use linkus_chess::transposition::TranspositionTable;
+use linkus_chess::SearchOptions;
+
+#[test]
+fn reads_test_suite_lines() {
+    let epd: Epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";"
+        .parse()
+        .unwrap();
+
+    assert_eq!(epd.board.to_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+    assert_eq!(epd.best_moves, vec![epd.board.parse_san("Qg6").unwrap()]);
+    assert!(epd.avoid_moves.is_empty());
+    assert_eq!(epd.id.as_deref(), Some("WAC.001"));
+    assert!(epd.is_solved_by(epd.best_moves[0]));
+
+    let epd: Epd = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - \
+                    am Nxe5 Ng5; bm Bb5 Bc4; c0 \"quiet; solid\"; hmvc 2; fmvn 3; ce 20;"
+        .parse()
+        .unwrap();
+
+    assert_eq!(epd.board.get_halfmove_clock(), 2);
+    assert_eq!(epd.board.get_fullmove_number(), 3);
+    assert_eq!(epd.best_moves.len(), 2);
+    assert_eq!(epd.avoid_moves.len(), 2);
+    assert_eq!(epd.comment.as_deref(), Some("quiet; solid"));
+    assert_eq!(epd.operations, vec![(String::from("ce"), vec![String::from("20")])]);
+    assert!(!epd.is_solved_by(epd.board.parse_san("Nxe5").unwrap()));
+    assert!(!epd.is_solved_by(epd.board.parse_san("d4").unwrap()));
+    assert!(epd.is_solved_by(epd.board.parse_san("Bc4").unwrap()));
+}
+
+#[test]
+fn reads_perft_counts() {
+    let suite = parse_epd(
+        "# comment\n\
+         \n\
+         rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902\n\
+         8/8/8/3pP1k1/8/8/8/5K2 b - - ;D1 8 ;D2 48\n",
+    )
+    .unwrap();
+
+    assert_eq!(suite.len(), 2);
+    assert_eq!(suite[0].perft, vec![(1, 20), (2, 400), (3, 8902)]);
+    assert_eq!(suite[1].perft, vec![(1, 8), (2, 48)]);
+    assert!(!suite[1].has_solution());
+}
+
+#[test]
+fn reports_errors() {
+    let error = parse_epd("8/8/8/8/8/8/8/8 w\n").err().unwrap();
+    assert!(matches!(error, EpdError::Syntax { line: 1, .. }));
+
+    let text = "4k3/8/8/8/8/8/8/4K3 w - - bm Kd2;\n4k3/8/8/8/8/8/8/4K3 w - - bm Ke3;\n";
+    let error = parse_epd(text).err().unwrap();
+    assert!(matches!(
+        error,
+        EpdError::Move { line: 2, reason: SanParseError::Illegal(_), .. }
+    ));
+
+    let error = parse_epd("4k3/8/8/8/8/8/8/4K3 w - - id \"open;\n").err().unwrap();
+    assert!(matches!(error, EpdError::Syntax { line: 1, .. }));
+
+    let error = parse_epd("4k3/8/8/8/8/8/8/4K3 w - - D1 many;\n").err().unwrap();
+    assert!(matches!(error, EpdError::Syntax { line: 1, .. }));
+
+    let error = parse_epd("4k3/8/8/8/8/8/8/4K4 w - - bm Kd2;\n").err().unwrap();
+    assert!(matches!(error, EpdError::Position { line: 1, .. }));
+}
+
+#[test]
+fn bench_counts_solved_positions() {
+    let suite = parse_epd(
+        "6k1/5ppp/8/8/8/8/8/3R2K1 w - - bm Rd8#; id \"back rank\";\n\
+         r5k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - am Rd8+; id \"defended\";\n\
+         r5k1/5ppp/8/8/8/8/8/3R2K1 w - - bm Rd2; id \"wrong\";\n\
+         4k3/8/8/8/8/8/8/4K3 w - - ;D1 5\n",
+    )
+    .unwrap();
+
+    let mut tt = TranspositionTable::new(1);
+    let (solved, failed) = bench_epd(
+        &suite,
+        &mut tt,
+        &SearchLimits::depth(3),
+        &SearchOptions::default(),
+    );
+    assert_eq!((solved, failed), (2, 1));
+}
@@ -0,0 +1,145 @@
+// fen.rs
+
+mod common;
+
+use linkus_chess::pgn::parse_pgn;
+use linkus_chess::board::FenParseError;
+use linkus_chess::{gen_tables, Board};
+use common::find_move;
+
+const POSITIONS: [&str; 6] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+#[test]
+fn round_trip_reference_positions() {
+    gen_tables();
+    for fen in POSITIONS {
+        assert_eq!(Board::new(fen).unwrap().to_fen(), fen);
+        assert_eq!(fen.parse::<Board>().unwrap().to_fen(), fen);
+    }
+}
+
+#[test]
+fn round_trip_epd_corpus() {
+    gen_tables();
+    let data = std::fs::read_to_string("tests/data/perft.epd").unwrap();
+    let mut count = 0;
+    for line in data.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fen = line.split(';').next().unwrap().trim();
+        assert_eq!(Board::new(fen).unwrap().to_fen(), fen);
+        count += 1;
+    }
+    assert!(count >= 300, "only {count} positions in the corpus");
+}
+
+#[test]
+fn round_trip_game_corpus() {
+    gen_tables();
+    let games = parse_pgn(include_str!("data/games.pgn")).unwrap();
+    let recorded: Vec<&str> = include_str!("data/games.fen")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let played: Vec<String> = games
+        .iter()
+        .flat_map(|game| game.boards())
+        .map(|board| board.to_fen())
+        .collect();
+    assert_eq!(played, recorded);
+
+    for fen in &recorded {
+        assert_eq!(Board::new(fen).unwrap().to_fen(), *fen);
+    }
+
+    // the corpus has to keep covering what perft.epd doesn't
+    let field = |fen: &&str, i: usize| fen.split(' ').nth(i).unwrap().to_string();
+    assert!(recorded.iter().filter(|fen| field(fen, 3) != "-").count() >= 20);
+    assert!(recorded.iter().any(|fen| field(fen, 4).parse::<u16>().unwrap() >= 30));
+    assert!(recorded.iter().any(|fen| field(fen, 5).parse::<u16>().unwrap() >= 70));
+}
+
+#[test]
+fn counters_follow_moves() {
+    gen_tables();
+    let mut board = Board::start();
+    let fens = [
+        ("e2e4", "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+        ("g8f6", "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"),
+        ("e1e2", "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2"),
+        ("f6e4", "rnbqkb1r/pppppppp/8/8/4n3/8/PPPPKPPP/RNBQ1BNR w kq - 0 3"),
+    ];
+    for (uci, fen) in fens {
+        board.make_move(find_move(&board, uci)).unwrap();
+        assert_eq!(board.to_fen(), fen);
+    }
+}
+
+#[test]
+fn malformed_fens_are_errors() {
+    gen_tables();
+    let malformed = [
+        "",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+        "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - a 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0",
+    ];
+    for fen in malformed {
+        assert!(Board::new(fen).is_err(), "{fen}");
+    }
+}
+
+#[test]
+fn castling_rights_need_king_and_rook_at_home() {
+    gen_tables();
+    let bad = [
+        "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+        "4k3/8/8/8/8/8/8/7K w K - 0 1",
+        "4k3/8/8/8/8/8/8/R3K3 w Kk - 0 1",
+        "r3k2r/8/8/8/8/8/8/R4K1R w KQkq - 0 1",
+        "1r2k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+    ];
+    for fen in bad {
+        assert_eq!(Board::new(fen).err(), Some(FenParseError::Castling), "{fen}");
+    }
+    assert!(Board::new("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").is_ok());
+    assert!(Board::new("1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1").is_ok());
+}
+
+#[test]
+fn en_passant_square_needs_a_pawn_that_just_pushed() {
+    gen_tables();
+    let bad = [
+        // wrong rank for the side to move
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 1",
+        "4k3/8/8/3pP3/8/8/8/4K3 b - d6 0 1",
+        // no pawn in front of the square
+        "4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1",
+        // the square or the one the pawn came from is taken
+        "4k3/8/3n4/3pP3/8/8/8/4K3 w - d6 0 1",
+        "4k3/3n4/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        "4k3/8/8/8/3Pp3/8/8/4K3 b - h3 0 1",
+    ];
+    for fen in bad {
+        assert_eq!(Board::new(fen).err(), Some(FenParseError::EnPassant), "{fen}");
+    }
+    assert!(Board::new("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").is_ok());
+    assert!(Board::new("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1").is_ok());
+}
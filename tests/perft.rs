@@ -0,0 +1,245 @@
+// perft.rs
+
+use linkus_chess::epd::parse_epd;
+use linkus_chess::piece_move::Move;
+use linkus_chess::{perft_driver, Board};
+
+/// Largest count checked by the default tier, deeper counts need the `slow`
+/// feature (`cargo test --release --features slow`).
+const FAST_NODE_LIMIT: u128 = 250_000;
+
+/// Check perft counts for depths 1, 2, .. of a position.
+fn check_counts(fen: &str, counts: &[u128], node_limit: u128) {
+    let mut board = Board::new(fen).unwrap();
+    for (i, &expected) in counts.iter().enumerate() {
+        if expected > node_limit {
+            break;
+        }
+        let depth = i as u8 + 1;
+        assert_eq!(
+            perft_driver(&mut board, depth),
+            expected,
+            "perft({depth}) of {fen}",
+        );
+    }
+}
+
+/// Check a single perft count.
+fn check_depth(fen: &str, depth: u8, expected: u128) {
+    let mut board = Board::new(fen).unwrap();
+    assert_eq!(perft_driver(&mut board, depth), expected, "perft({depth}) of {fen}");
+}
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const START_COUNTS: [u128; 6] = [20, 400, 8902, 197281, 4865609, 119060324];
+
+const KIWIPETE: &str =
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const KIWIPETE_COUNTS: [u128; 5] = [48, 2039, 97862, 4085603, 193690690];
+
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_3_COUNTS: [u128; 6] = [14, 191, 2812, 43238, 674624, 11030083];
+
+const POSITION_4: &str =
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED: &str =
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_4_COUNTS: [u128; 5] = [6, 264, 9467, 422333, 15833292];
+
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_5_COUNTS: [u128; 5] = [44, 1486, 62379, 2103487, 89941194];
+
+const POSITION_6: &str =
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+const POSITION_6_COUNTS: [u128; 5] = [46, 2079, 89890, 3894594, 164075551];
+
+/// Positions built around one en passant, castling or promotion rule each,
+/// with counts checked against the perft of shakmaty.
+const EDGE_CASES: [(&str, u8, u128); 14] = [
+    // en passant that would expose the king
+    ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1134888),
+    ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1015133),
+    // en passant capture gives check
+    ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467),
+    // castling gives check
+    ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661072),
+    ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803711),
+    // castling rights lost and castling through attacked squares
+    ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1274206),
+    ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1720476),
+    // promotion out of check and promotion giving check
+    ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3821001),
+    ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217342),
+    ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92683),
+    // discovered check
+    ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1004658),
+    // stalemate and checkmate
+    ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217),
+    ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567584),
+    ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23527),
+];
+
+fn check_epd_suite(node_limit: u128) {
+    let suite = parse_epd(include_str!("data/perft.epd")).unwrap();
+    let mut checked = 0;
+    for epd in suite {
+        let fen = epd.board.to_fen();
+        let mut board = epd.board;
+        for (depth, expected) in epd.perft {
+            if expected > node_limit {
+                continue;
+            }
+            assert_eq!(
+                perft_driver(&mut board, depth),
+                expected,
+                "perft({depth}) of {fen}",
+            );
+            checked += 1;
+        }
+    }
+    assert!(checked > 0);
+}
+
+/// Legal moves by trying every pseudo legal move, sorted to compare lists
+/// built in a different order.
+fn filtered_moves(board: &mut Board) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for &mv in board.pseudo_legal_moves().iter() {
+        if let Some(undo) = board.make_move(mv) {
+            board.unmake_move(mv, undo);
+            moves.push(mv);
+        }
+    }
+    sorted(moves)
+}
+
+/// Call `visit` on `board` and on every position up to `depth` plies after it.
+fn walk(board: &mut Board, depth: u8, visit: &mut impl FnMut(&mut Board)) {
+    visit(board);
+    if depth > 0 {
+        for mv in board.legal_moves().into_vec() {
+            let undo = board.make_move(mv).unwrap();
+            walk(board, depth - 1, visit);
+            board.unmake_move(mv, undo);
+        }
+    }
+}
+
+/// Call `visit` on every corpus position and the positions up to `depth`
+/// plies after it.
+fn walk_corpus(depth: u8, visit: &mut impl FnMut(&mut Board)) {
+    for epd in parse_epd(include_str!("data/perft.epd")).unwrap() {
+        let mut board = epd.board;
+        walk(&mut board, depth, visit);
+    }
+}
+
+fn sorted(mut moves: Vec<Move>) -> Vec<Move> {
+    moves.sort_by_key(|mv| mv.to_string());
+    moves
+}
+
+#[test]
+fn evasions_match_filtered_pseudo_legal_moves() {
+    let mut compared = 0;
+    walk_corpus(2, &mut |board| {
+        if board.is_in_check() {
+            let evasions = sorted(board.evasions().into_vec());
+            assert_eq!(evasions, filtered_moves(board), "evasions of {}", board.to_fen());
+            compared += 1;
+        }
+    });
+    assert!(compared > 1000, "only {compared} positions in check");
+}
+
+#[test]
+fn captures_match_pseudo_legal_moves() {
+    let mut promotions = 0;
+    walk_corpus(1, &mut |board| {
+        let expected: Vec<Move> = board
+            .pseudo_legal_moves()
+            .into_vec()
+            .into_iter()
+            .filter(|mv| mv.is_capture() || mv.is_promotion())
+            .collect();
+        promotions += expected.iter().filter(|mv| mv.is_promotion()).count();
+        assert_eq!(
+            sorted(board.pseudo_legal_captures().into_vec()),
+            sorted(expected),
+            "captures of {}",
+            board.to_fen(),
+        );
+    });
+    assert!(promotions > 1000, "only {promotions} promotions");
+}
+
+#[test]
+fn start_position() {
+    check_counts(START, &START_COUNTS, FAST_NODE_LIMIT);
+}
+
+#[test]
+fn kiwipete() {
+    check_counts(KIWIPETE, &KIWIPETE_COUNTS, FAST_NODE_LIMIT);
+}
+
+#[test]
+fn position_3() {
+    check_counts(POSITION_3, &POSITION_3_COUNTS, FAST_NODE_LIMIT);
+}
+
+#[test]
+fn position_4() {
+    check_counts(POSITION_4, &POSITION_4_COUNTS, FAST_NODE_LIMIT);
+    check_counts(POSITION_4_MIRRORED, &POSITION_4_COUNTS, FAST_NODE_LIMIT);
+}
+
+#[test]
+fn position_5() {
+    check_counts(POSITION_5, &POSITION_5_COUNTS, FAST_NODE_LIMIT);
+}
+
+#[test]
+fn position_6() {
+    check_counts(POSITION_6, &POSITION_6_COUNTS, FAST_NODE_LIMIT);
+}
+
+#[test]
+fn edge_cases() {
+    for (fen, depth, expected) in EDGE_CASES {
+        if expected <= FAST_NODE_LIMIT {
+            check_depth(fen, depth, expected);
+        }
+    }
+}
+
+#[test]
+fn epd_suite() {
+    check_epd_suite(50_000);
+}
+
+#[cfg(feature = "slow")]
+#[test]
+fn reference_positions_deep() {
+    check_counts(START, &START_COUNTS, u128::MAX);
+    check_counts(KIWIPETE, &KIWIPETE_COUNTS, u128::MAX);
+    check_counts(POSITION_3, &POSITION_3_COUNTS, u128::MAX);
+    check_counts(POSITION_4, &POSITION_4_COUNTS, u128::MAX);
+    check_counts(POSITION_4_MIRRORED, &POSITION_4_COUNTS, u128::MAX);
+    check_counts(POSITION_5, &POSITION_5_COUNTS, u128::MAX);
+    check_counts(POSITION_6, &POSITION_6_COUNTS, u128::MAX);
+}
+
+#[cfg(feature = "slow")]
+#[test]
+fn edge_cases_deep() {
+    for (fen, depth, expected) in EDGE_CASES {
+        check_depth(fen, depth, expected);
+    }
+}
+
+#[cfg(feature = "slow")]
+#[test]
+fn epd_suite_deep() {
+    check_epd_suite(u128::MAX);
+}
@@ -0,0 +1,177 @@
+// pgn.rs
+
+use linkus_chess::pgn::{parse_pgn, Game, PgnError};
+use linkus_chess::san::SanParseError;
+use linkus_chess::{gen_tables, Board};
+
+const OPERA_GAME: &str = r#"[Event "Casual game"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Morphy, Paul"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {This is a weak move already.} 4. dxe5 Bxf3 5. Qxf3
+dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5 10. Nxb5! cxb5 11. Bxb5+ Nbd7 12.
+O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0
+"#;
+
+#[test]
+fn reads_tags_and_moves() {
+    gen_tables();
+    let game: Game = OPERA_GAME.parse().unwrap();
+
+    assert_eq!(game.tag("White"), Some("Morphy, Paul"));
+    assert_eq!(game.tag("Date"), Some("1858.??.??"));
+    assert_eq!(game.tag("ECO"), None);
+    assert_eq!(game.result, "1-0");
+    assert_eq!(game.moves().len(), 33);
+    assert_eq!(game.line.moves[5].comment.as_deref(), Some("This is a weak move already."));
+    assert_eq!(game.line.moves[18].nags, vec![1]);
+
+    let boards = game.boards();
+    assert_eq!(boards.len(), 34);
+    assert_eq!(boards[0].to_fen(), Board::start().to_fen());
+    let last = boards.last().unwrap();
+    assert!(last.is_in_check() && last.legal_moves().is_empty());
+    assert_eq!(last.to_fen(), game.board().to_fen());
+}
+
+#[test]
+fn round_trip() {
+    gen_tables();
+    let game: Game = OPERA_GAME.parse().unwrap();
+    let pgn = game.to_pgn();
+    assert!(pgn.lines().all(|line| line.len() <= 80));
+    assert!(pgn.contains("3. d4 Bg4 {This is a weak move already.} 4. dxe5"));
+    assert!(pgn.contains("10. Nxb5 $1 cxb5"));
+    assert!(pgn.contains("17. Rd8#"));
+    assert!(pgn.ends_with("1-0\n"));
+
+    let again: Game = pgn.parse().unwrap();
+    assert_eq!(again.tags, game.tags);
+    assert_eq!(again.moves(), game.moves());
+    assert_eq!(again.to_pgn(), pgn);
+}
+
+#[test]
+fn variations_comments_and_nags() {
+    gen_tables();
+    let text = "{Opening} 1. e4 $1 e5 (1... c5 2. Nf3 (2. c3) 2... d6) ; rest of line\n\
+                2. Nf3!? Nc6?! 3. Bb5 a6 *";
+    let game: Game = text.parse().unwrap();
+
+    assert_eq!(game.line.comment.as_deref(), Some("Opening"));
+    assert_eq!(game.moves().len(), 6);
+    assert_eq!(game.line.moves[0].nags, vec![1]);
+    assert_eq!(game.line.moves[1].comment.as_deref(), Some("rest of line"));
+    assert_eq!(game.line.moves[2].nags, vec![5]);
+    assert_eq!(game.line.moves[3].nags, vec![6]);
+    assert_eq!(game.result, "*");
+
+    let sicilian = &game.line.moves[1].variations[0];
+    assert_eq!(sicilian.moves.len(), 3);
+    assert_eq!(sicilian.moves[1].variations[0].moves.len(), 1);
+
+    let pgn = game.to_pgn();
+    assert!(pgn.contains(
+        "{Opening} 1. e4 $1 e5 {rest of line} (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 $5"
+    ));
+    let again: Game = pgn.parse().unwrap();
+    assert_eq!(again.to_pgn(), pgn);
+}
+
+#[test]
+fn several_games_and_fen_tag() {
+    gen_tables();
+    let text = "[Event \"one\"]\n\n1. d4 d5 1/2-1/2\n\n\
+                [Event \"two\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 40\"]\n\n\
+                % escaped line\n40... Kd7 41. e4 0-1\n\n\
+                [Event \"three \\\"quoted\\\"\"]\n\n*";
+    let games = parse_pgn(text).unwrap();
+
+    assert_eq!(games.len(), 3);
+    assert_eq!(games[0].result, "1/2-1/2");
+    assert_eq!(games[1].moves().len(), 2);
+    assert_eq!(games[1].board().to_fen(), "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 41");
+    assert!(games[1].to_pgn().contains("40... Kd7 41. e4 0-1"));
+    assert_eq!(games[2].tag("Event"), Some("three \"quoted\""));
+    assert!(games[2].to_pgn().contains("[Event \"three \\\"quoted\\\"\"]"));
+    assert!(games[2].moves().is_empty());
+}
+
+#[test]
+fn builds_games() {
+    gen_tables();
+    let start = Board::new("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    let mut game = Game::new(start.clone());
+    assert_eq!(game.tag("FEN"), Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+
+    let mv = start.parse_san("e4").unwrap();
+    game.push(mv).unwrap();
+    assert!(matches!(game.push(mv), Err(SanParseError::Illegal(_))));
+
+    // the result tag follows the result
+    game.result = String::from("1/2-1/2");
+    let again: Game = game.to_pgn().parse().unwrap();
+    assert_eq!(again.moves(), vec![mv]);
+    assert_eq!(again.tag("Result"), Some("1/2-1/2"));
+    assert_eq!(again.result, "1/2-1/2");
+
+    // and is added when the tags leave it out
+    game.tags.retain(|(name, _)| name != "Result");
+    game.result = String::from("1-0");
+    let again: Game = game.to_pgn().parse().unwrap();
+    assert_eq!(again.tag("Result"), Some("1-0"));
+}
+
+#[test]
+fn reports_illegal_moves() {
+    gen_tables();
+    let text = "[Event \"x\"]\n\n1. e4 e5 2. Nf3 Nc6\n3. Bb5 Nf6 4. Bxe8 *";
+    match parse_pgn(text) {
+        Err(PgnError::IllegalMove { line, ply, san, reason }) => {
+            assert_eq!((line, ply, san.as_str()), (4, 7, "Bxe8"));
+            assert_eq!(reason, SanParseError::Illegal(String::from("Bxe8")));
+        },
+        _ => panic!("expected an illegal move"),
+    }
+
+    // ply counts continue into variations
+    let text = "1. e4 e5 (1... c5 2. Nf3 Nf3) *";
+    assert!(matches!(
+        parse_pgn(text),
+        Err(PgnError::IllegalMove { ply: 4, reason: SanParseError::Illegal(_), .. })
+    ));
+
+    let text = "[FEN \"4k3/8/8/8/8/8/4K3/R6R w - - 0 1\"]\n\n1. Rd1 *";
+    assert!(matches!(
+        parse_pgn(text),
+        Err(PgnError::IllegalMove { ply: 1, reason: SanParseError::Ambiguous(_), .. })
+    ));
+}
+
+#[test]
+fn reports_syntax_errors() {
+    gen_tables();
+    let errors = [
+        ("[Event \"x]\n\n*", 1),
+        ("[Event]\n\n*", 1),
+        ("1. e4 {never closed", 1),
+        ("1. e4 e5\n2. Nf3 (2. Bc4 *", 2),
+        ("1. e4 e5)\n*", 1),
+        ("(1. e4) *", 1),
+        ("$1 1. e4 *", 1),
+        ("1. e4\n<e5> *", 2),
+    ];
+    for (text, expected) in errors {
+        match parse_pgn(text) {
+            Err(PgnError::Syntax { line, .. }) => assert_eq!(line, expected, "{text}"),
+            _ => panic!("expected a syntax error for {text}"),
+        }
+    }
+
+    assert!(matches!(parse_pgn("[FEN \"8/8 w - - 0 1\"]\n\n*"), Err(PgnError::Fen { line: 1, .. })));
+    assert!(parse_pgn("").unwrap().is_empty());
+}
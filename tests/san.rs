@@ -0,0 +1,125 @@
+// san.rs
+
+use linkus_chess::san::SanParseError;
+use linkus_chess::{gen_tables, Board};
+
+/// SAN of every legal move of a position, sorted.
+fn all_san(fen: &str) -> Vec<String> {
+    gen_tables();
+    let board = Board::new(fen).unwrap();
+    let mut san: Vec<String> = board
+        .legal_moves()
+        .iter()
+        .map(|&mv| board.move_to_san(mv))
+        .collect();
+    san.sort();
+    san
+}
+
+fn has(fen: &str, san: &str) -> bool {
+    all_san(fen).iter().any(|s| s == san)
+}
+
+#[test]
+fn pieces_pawns_and_captures() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert!(has(start, "e4"));
+    assert!(has(start, "Nf3"));
+    assert_eq!(all_san(start).len(), 20);
+
+    let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+    assert!(has(fen, "exd5"));
+    assert!(has(fen, "Bb5+"));
+}
+
+#[test]
+fn disambiguation() {
+    // knights on b1 and f3 both reach d2
+    let fen = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+    assert!(has(fen, "Nbd2"));
+    assert!(has(fen, "Nfd2"));
+
+    // rooks on a1 and a5 share a file
+    let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+    assert!(has(fen, "R1a3"));
+    assert!(has(fen, "R5a3"));
+
+    // queens on a1, a3 and c1 all reach b2
+    let fen = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+    assert!(has(fen, "Qa1b2"));
+    assert!(has(fen, "Q3b2"));
+    assert!(has(fen, "Qcb2"));
+}
+
+#[test]
+fn castling_promotion_en_passant_and_mate() {
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert!(has(fen, "O-O"));
+    assert!(has(fen, "O-O-O"));
+
+    let fen = "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+    assert!(has(fen, "a8=Q"));
+    assert!(has(fen, "axb8=Q+"));
+    assert!(has(fen, "axb8=N"));
+
+    let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+    assert!(has(fen, "exd6"));
+
+    let fen = "6k1/5ppp/8/8/8/8/8/3R2K1 w - - 0 1";
+    assert!(has(fen, "Rd8#"));
+}
+
+#[test]
+fn parse_variants() {
+    gen_tables();
+    let board = Board::new("r3k2r/1P6/8/8/8/5N2/8/R3K1NR w KQkq - 0 1").unwrap();
+
+    let san = |text: &str| board.move_to_san(board.parse_san(text).unwrap());
+    assert_eq!(san("O-O-O"), "O-O-O");
+    assert_eq!(san("0-0-0"), "O-O-O");
+    assert_eq!(san("Nh2"), "Nh2");
+    assert_eq!(san("Nfh2"), "Nh2");
+    assert_eq!(san("Nf3h2"), "Nh2");
+    assert_eq!(san("bxa8=Q+"), "bxa8=Q+");
+    assert_eq!(san("bxa8Q"), "bxa8=Q+");
+    assert_eq!(san("b8=N!?"), "b8=N");
+    assert_eq!(san("Ra1a7"), "Ra7");
+}
+
+#[test]
+fn parse_errors() {
+    gen_tables();
+    let board = Board::new("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+
+    assert!(matches!(board.parse_san("Nd2"), Err(SanParseError::Ambiguous(_))));
+    assert!(board.parse_san("Nd4+").is_ok());
+    assert!(board.parse_san("Ne5").is_ok());
+    assert!(matches!(board.parse_san("Nb6"), Err(SanParseError::Illegal(_))));
+    assert!(matches!(board.parse_san("O-O"), Err(SanParseError::Illegal(_))));
+    assert!(matches!(board.parse_san("e9"), Err(SanParseError::Syntax(_))));
+    assert!(matches!(board.parse_san(""), Err(SanParseError::Syntax(_))));
+    assert!(matches!(board.parse_san("N"), Err(SanParseError::Syntax(_))));
+    assert!(matches!(board.parse_san("Nzz3"), Err(SanParseError::Syntax(_))));
+}
+
+#[test]
+fn round_trip_epd_corpus() {
+    gen_tables();
+    let data = std::fs::read_to_string("tests/data/perft.epd").unwrap();
+    for line in data.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fen = line.split(';').next().unwrap().trim();
+        let board = Board::new(fen).unwrap();
+        let legal = board.legal_moves();
+
+        let mut seen = Vec::new();
+        for &mv in legal.iter() {
+            let san = board.move_to_san(mv);
+            assert_eq!(board.parse_san(&san), Ok(mv), "{san} in {fen}");
+            assert!(!seen.contains(&san), "{san} twice in {fen}");
+            seen.push(san);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// search.rs
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+use linkus_chess::color::Color;
+use linkus_chess::evaluate::eval;
+use linkus_chess::negamax::{quiescence, SearchInfo, SearchSignals, Verbosity, INFINITY, MATE};
+use linkus_chess::piece_move::NO_MOVE;
+use linkus_chess::time_manager::SearchLimits;
+use linkus_chess::transposition::TranspositionTable;
+use linkus_chess::board::STARTPOS;
+use linkus_chess::{negamax, Board, SearchOptions, SearchResult};
+
+fn search(fen: &str, depth: u8, options: &SearchOptions) -> SearchResult {
+    let mut board = Board::new(fen).unwrap();
+    let mut tt = TranspositionTable::new(1);
+    negamax(
+        &mut board,
+        &mut tt,
+        &SearchLimits::depth(depth),
+        options,
+        &SearchSignals::default(),
+        Verbosity::Quiet,
+    )
+}
+
+#[test]
+fn mated_root() {
+    let result = search("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1", 5, &SearchOptions::default());
+    assert_eq!(result.best_move, NO_MOVE);
+    assert_eq!(result.score, -MATE);
+    assert!(result.pv.is_empty());
+}
+
+#[test]
+fn stalemated_root() {
+    let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 5, &SearchOptions::default());
+    assert_eq!(result.best_move, NO_MOVE);
+    assert_eq!(result.score, 0);
+    assert!(result.pv.is_empty());
+}
+
+#[test]
+fn ponderhit_starts_the_clock() {
+    let mut board = Board::new(STARTPOS).unwrap();
+    let mut tt = TranspositionTable::new(1);
+    let limits = SearchLimits { movetime: Some(300), ponder: true, ..SearchLimits::default() };
+    let options = SearchOptions::default();
+    let signals = SearchSignals::default();
+    signals.pondering.store(true, Ordering::Relaxed);
+
+    thread::scope(|scope| {
+        let search = scope.spawn(|| {
+            negamax(&mut board, &mut tt, &limits, &options, &signals, Verbosity::Quiet)
+        });
+
+        // ponder for longer than the whole budget before the opponent moves
+        thread::sleep(Duration::from_millis(500));
+        let ponderhit = Instant::now();
+        signals.pondering.store(false, Ordering::Relaxed);
+        let result = search.join().unwrap();
+
+        // 300 ms less the move overhead, counted from the ponderhit
+        assert!(ponderhit.elapsed() >= Duration::from_millis(200));
+        assert_ne!(result.best_move, NO_MOVE);
+    });
+}
+
+#[test]
+fn multi_pv_lines_are_distinct_and_sorted() {
+    let options = SearchOptions { multi_pv: 4, ..SearchOptions::default() };
+    let result = search("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", 5, &options);
+    assert_eq!(result.lines.len(), 4);
+    assert_eq!(result.lines[0], (result.best_move, result.score));
+
+    for (i, (mv, _)) in result.lines.iter().enumerate() {
+        assert!(!result.lines[..i].iter().any(|(other, _)| other == mv), "{mv} twice");
+    }
+    assert!(result.lines.windows(2).all(|pair| pair[0].1 >= pair[1].1), "{:?}", result.lines);
+
+    // never more lines than legal moves
+    let options = SearchOptions { multi_pv: 10, ..SearchOptions::default() };
+    let result = search("7k/8/8/8/8/8/8/K7 w - - 0 1", 3, &options);
+    assert_eq!(result.lines.len(), 3);
+}
+
+#[test]
+fn contempt_sets_the_draw_score() {
+    for contempt in [50, -50] {
+        let options = SearchOptions { contempt, ..SearchOptions::default() };
+
+        // a draw is worth less than nothing to the engine with contempt,
+        // at the root as well as further down
+        assert_eq!(search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3, &options).score, -contempt);
+        assert_eq!(search("8/8/8/4k3/8/8/8/4K3 w - - 0 1", 4, &options).score, -contempt);
+        assert_eq!(search("8/8/8/4k3/8/8/8/4K3 b - - 0 1", 5, &options).score, -contempt);
+    }
+}
+
+/// Quiescence score of `fen` with a full window.
+fn quiesce(fen: &str) -> i32 {
+    let mut board = Board::new(fen).unwrap();
+    let mut tt = TranspositionTable::new(1);
+    let signals = SearchSignals::default();
+    let mut info = SearchInfo::new(
+        &mut tt,
+        &SearchLimits::default(),
+        &SearchOptions::default(),
+        &signals,
+        board.get_to_move(),
+    );
+    quiescence(&mut board, &mut info, -INFINITY, INFINITY, 0)
+}
+
+#[test]
+fn quiescence_takes_a_hanging_piece() {
+    // the queen hangs to the pawn and black has nothing to take back with
+    let fen = "4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1";
+    let after = Board::new("4k3/8/8/3P4/8/8/8/4K3 b - - 0 1").unwrap();
+    assert!(eval(&Board::new(fen).unwrap(), Color::WHITE) < 0);
+    assert_eq!(quiesce(fen), eval(&after, Color::WHITE));
+
+    // a defended knight is not worth the rook
+    let fen = "4k3/8/4p3/3n4/8/8/8/3RK3 w - - 0 1";
+    assert_eq!(quiesce(fen), eval(&Board::new(fen).unwrap(), Color::WHITE));
+}
+
+#[test]
+fn g8a8_is_a_move() {
+    // g8a8 has the encoding the no move placeholder used to have
+    let fen = "6r1/8/8/8/K1k5/8/8/8 b - - 0 1";
+    let board = Board::new(fen).unwrap();
+    let g8a8 = board.legal_moves().into_vec().into_iter().find(|mv| mv.to_string() == "g8a8");
+    assert!(g8a8.is_some_and(|mv| mv != NO_MOVE));
+
+    let result = search(fen, 4, &SearchOptions::default());
+    assert_eq!(result.best_move.to_string(), "g8a8");
+    assert_eq!(result.score, MATE - 1);
+}
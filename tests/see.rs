@@ -0,0 +1,52 @@
+// see.rs
+
+mod common;
+
+use linkus_chess::{gen_tables, Board};
+use common::find_move;
+
+fn see(fen: &str, uci: &str) -> i32 {
+    gen_tables();
+    let board = Board::new(fen).unwrap();
+    board.see(find_move(&board, uci))
+}
+
+#[test]
+fn undefended_capture() {
+    assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+}
+
+#[test]
+fn defended_capture() {
+    assert_eq!(see("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1", "e1e5"), -400);
+}
+
+#[test]
+fn x_ray_attackers() {
+    assert_eq!(
+        see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"),
+        -200,
+    );
+}
+
+#[test]
+fn king_cannot_recapture_defended_piece() {
+    assert_eq!(see("8/8/4k3/4p3/8/8/8/4RK2 w - - 0 1", "e1e5"), -400);
+    assert_eq!(see("8/8/4k3/4p3/8/8/4R3/4QK2 w - - 0 1", "e2e5"), 100);
+}
+
+#[test]
+fn quiet_move_to_attacked_square() {
+    assert_eq!(see("4k3/8/8/3p4/8/8/8/4KQ2 w - - 0 1", "f1c4"), -1000);
+
+    let board = Board::new("4k3/8/8/3p4/8/8/8/4KQ2 w - - 0 1").unwrap();
+    assert!(!board.see_ge(find_move(&board, "f1c4"), 0));
+    assert!(board.see_ge(find_move(&board, "f1f5"), 0));
+}
+
+#[test]
+fn en_passant_and_promotion() {
+    assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+    assert_eq!(see("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), 900);
+    assert_eq!(see("8/Pk6/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), -100);
+}
@@ -0,0 +1,76 @@
+// uci.rs
+
+use linkus_chess::negamax::{SearchSignals, Verbosity, MATE};
+use linkus_chess::parse::{parse_command, UciCommand, UciError};
+use linkus_chess::time_manager::SearchLimits;
+use linkus_chess::transposition::TranspositionTable;
+use linkus_chess::{negamax, Board, SearchOptions, SearchResult};
+
+fn limits_of(input: &str) -> SearchLimits {
+    match parse_command(input) {
+        Ok(UciCommand::Go(limits)) => limits,
+        other => panic!("{input} gave {other:?}"),
+    }
+}
+
+fn go(fen: &str, input: &str) -> SearchResult {
+    let mut board = Board::new(fen).unwrap();
+    let mut tt = TranspositionTable::new(1);
+    negamax(
+        &mut board,
+        &mut tt,
+        &limits_of(input),
+        &SearchOptions::default(),
+        &SearchSignals::default(),
+        Verbosity::Quiet,
+    )
+}
+
+#[test]
+fn go_mate_is_depth_limited() {
+    let limits = limits_of("go mate 3");
+    assert_eq!(limits.mate, Some(3));
+    assert!(!limits.infinite);
+    assert!(parse_command("go mate 0").is_err());
+    assert_eq!(parse_command("go mate"), Err(UciError::MissingArgument(String::from("mate"))));
+
+    // Rb7 then Ra8 mate, or Ra7 then Rb8
+    let result = go("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1", "go mate 2");
+    assert_eq!(result.score, MATE - 3);
+    assert!(result.depth <= 3);
+
+    // no mate that short, the search still ends
+    let result = go("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1", "go mate 1");
+    assert_eq!(result.depth, 1);
+}
+
+#[test]
+fn go_searchmoves_filters_the_root() {
+    let limits = limits_of("go searchmoves a2a3 h2h3");
+    assert_eq!(limits.searchmoves, vec![String::from("a2a3"), String::from("h2h3")]);
+    assert!(!limits.infinite);
+    assert!(limits.depth.is_some());
+
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    let result = go(start, "go searchmoves a2a3 h2h3 depth 4");
+    assert!(["a2a3", "h2h3"].contains(&result.best_move.to_string().as_str()));
+
+    // with MultiPV the lines only use the moves given
+    let mut board = Board::new(start).unwrap();
+    let mut tt = TranspositionTable::new(1);
+    let result = negamax(
+        &mut board,
+        &mut tt,
+        &limits_of("go searchmoves g1f3 b1c3 e2e4 depth 3"),
+        &SearchOptions { multi_pv: 5, ..SearchOptions::default() },
+        &SearchSignals::default(),
+        Verbosity::Quiet,
+    );
+    let mut moves: Vec<String> = result.lines.iter().map(|(mv, _)| mv.to_string()).collect();
+    moves.sort();
+    assert_eq!(moves, ["b1c3", "e2e4", "g1f3"]);
+
+    // none of the moves is legal, search them all
+    let result = go(start, "go searchmoves e2e5 depth 2");
+    assert!(Board::new(start).unwrap().legal_moves().contains(result.best_move));
+}
@@ -0,0 +1,55 @@
+// main.rs
+
+use std::io::BufRead;
+use std::process::ExitCode;
+use shakmaty::fen::Fen;
+use shakmaty::{perft, CastlingMode, Chess};
+
+/// Read `<fen> ;D<depth> <nodes> ...` lines from stdin and count every depth
+/// again with shakmaty. Prints each count that differs and fails if any does.
+fn main() -> ExitCode {
+    let mut checked = 0;
+    let mut wrong = 0;
+    for (i, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = line.expect("stdin is not readable");
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or_default().trim();
+        let position = fen
+            .parse::<Fen>()
+            .ok()
+            .and_then(|f| f.into_position::<Chess>(CastlingMode::Standard).ok());
+        let position = match position {
+            Some(position) => position,
+            None => {
+                println!("line {}: invalid position {fen}", i + 1);
+                wrong += 1;
+                continue;
+            },
+        };
+
+        for field in fields {
+            let mut words = field.split_whitespace();
+            let depth = words.next().and_then(|d| d.strip_prefix('D')).and_then(|d| d.parse().ok());
+            let nodes = words.next().and_then(|n| n.parse::<u64>().ok());
+            let (Some(depth), Some(nodes)) = (depth, nodes) else {
+                println!("line {}: unreadable count '{}'", i + 1, field.trim());
+                wrong += 1;
+                continue;
+            };
+
+            let expected = perft(&position, depth);
+            if expected != nodes {
+                println!("line {}: perft({depth}) of {fen} is {expected}, not {nodes}", i + 1);
+                wrong += 1;
+            }
+            checked += 1;
+        }
+    }
+
+    println!("{checked} counts checked, {wrong} wrong");
+    if wrong == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}